@@ -1,11 +1,30 @@
 // src/ai_renderer.rs
 
+use crate::backend::fit::{bucket_size, fit_image, FitMode, ResizeFilter};
+use crate::backend::retry::RetryPolicy;
+use crate::backend::{AssetSpec, CancelToken, GenError, GenInfo, GenProgress, GeneratedImage, ImageBackend};
 use serde::{Serialize, Deserialize};
 use reqwest::blocking::Client;
 use image::{DynamicImage, ImageFormat};
 use base64::{Engine as _, engine::general_purpose};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
 
-const API_URL: &str = "http://127.0.0.1:7860/sdapi/v1/txt2img";
+/// Адрес `stable-diffusion-webui` по умолчанию.
+pub const DEFAULT_BASE_URL: &str = "http://127.0.0.1:7860";
+
+/// Родное разрешение моделей Stable Diffusion 1.5.
+pub const DEFAULT_NATIVE_RESOLUTION: u32 = 512;
+
+/// Сколько ждать установки соединения по умолчанию.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Сколько ждать ответа на запрос генерации по умолчанию.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// Как часто опрашивать `/sdapi/v1/progress` во время генерации.
+const PROGRESS_POLL_INTERVAL: Duration = Duration::from_millis(500);
 
 #[derive(Serialize)]
 struct Txt2ImgRequest {
@@ -16,49 +35,301 @@ struct Txt2ImgRequest {
     height: u32,
     cfg_scale: f32,
     sampler_name: String,
+    seed: i64,
+}
+
+#[derive(Serialize)]
+struct Img2ImgRequest {
+    #[serde(flatten)]
+    base: Txt2ImgRequest,
+    init_images: Vec<String>,
+    denoising_strength: f32,
 }
 
 #[derive(Deserialize)]
 struct Txt2ImgResponse {
     images: Vec<String>,
+    /// JSON-строка с параметрами, с которыми сервер реально выполнил генерацию.
+    #[serde(default)]
+    info: String,
+}
+
+#[derive(Deserialize, Default)]
+struct Txt2ImgInfo {
+    seed: Option<i64>,
+    sd_model_hash: Option<String>,
 }
 
+#[derive(Deserialize)]
+struct OptionsResponse {
+    sd_model_checkpoint: String,
+}
+
+#[derive(Deserialize)]
+struct ProgressResponse {
+    progress: f32,
+    eta_relative: f32,
+    current_image: Option<String>,
+}
+
+/// Клиент AUTOMATIC1111 `stable-diffusion-webui`, одна из реализаций `ImageBackend`.
 pub struct AiRenderer {
     client: Client,
+    base_url: String,
+    retry: RetryPolicy,
+    /// Разрешение, на котором обучена модель; вокруг него подбирается размер генерации.
+    native_resolution: u32,
+    /// Имя загруженного на сервере чекпойнта; запрашивается один раз.
+    model: OnceLock<String>,
+    /// Сколько раз генерации прерывались. Ответ, во время которого счетчик изменился,
+    /// недорисован, хотя сервер и отвечает на него статусом 200.
+    interrupts: AtomicU64,
 }
 
 impl AiRenderer {
     pub fn new() -> Self {
-        Self { client: Client::new() }
+        Self::with_base_url(DEFAULT_BASE_URL)
     }
 
-    pub fn generate_image(&self, prompt: &str, width: u32, height: u32) -> Result<DynamicImage, Box<dyn std::error::Error>> {
-        println!("AI Renderer: Sending prompt: '{}'", prompt);
+    /// Клиент для сервера по другому адресу, например `http://gpu-box:7860`.
+    pub fn with_base_url(base_url: &str) -> Self {
+        Self {
+            client: build_client(DEFAULT_CONNECT_TIMEOUT, DEFAULT_REQUEST_TIMEOUT),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            retry: RetryPolicy::default(),
+            native_resolution: DEFAULT_NATIVE_RESOLUTION,
+            model: OnceLock::new(),
+            interrupts: AtomicU64::new(0),
+        }
+    }
 
+    /// Задает таймауты установки соединения и ответа на запрос.
+    pub fn with_timeouts(mut self, connect: Duration, request: Duration) -> Self {
+        self.client = build_client(connect, request);
+        self
+    }
+
+    /// Задает родное разрешение модели: 512 для SD 1.5, 1024 для SDXL.
+    pub fn with_native_resolution(mut self, native_resolution: u32) -> Self {
+        self.native_resolution = native_resolution;
+        self
+    }
+
+    /// Задает политику повторов при временных ошибках.
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Быстрая проверка, что сервер запущен и отвечает на API-запросы.
+    pub fn is_available(&self) -> bool {
+        self.client
+            .get(self.endpoint("/sdapi/v1/progress"))
+            .timeout(Duration::from_secs(2))
+            .send()
+            .map(|response| response.status().is_success())
+            .unwrap_or(false)
+    }
+
+    /// Текущий прогресс сервера. Сервер выполняет генерации по одной,
+    /// поэтому ответ относится к той, что выполняется прямо сейчас.
+    fn fetch_progress(&self, spec: &AssetSpec) -> Option<GenProgress> {
+        let response: ProgressResponse = self.client
+            .get(self.endpoint("/sdapi/v1/progress"))
+            .timeout(PROGRESS_POLL_INTERVAL * 4)
+            .send()
+            .ok()?
+            .json()
+            .ok()?;
+
+        let preview = response
+            .current_image
+            .and_then(|data| decode_png(&data).ok())
+            .map(|image| Arc::new(fit_image(&image, spec.width, spec.height, spec.fit, ResizeFilter::Linear)));
+        Some(GenProgress {
+            fraction: response.progress.clamp(0.0, 1.0),
+            eta: (response.eta_relative > 0.0).then(|| Duration::from_secs_f32(response.eta_relative)),
+            preview,
+        })
+    }
+
+    /// Генерация с повторами при временных ошибках; после отмены `cancel` повторов нет.
+    fn run_generation(&self, spec: &AssetSpec, cancel: &CancelToken) -> Result<GeneratedImage, GenError> {
+        self.retry.run(|| cancel.is_cancelled(), || self.request_generation(spec))
+    }
+
+    /// Выполняет txt2img или, если у ассета есть исходная картинка, img2img.
+    fn request_generation(&self, spec: &AssetSpec) -> Result<GeneratedImage, GenError> {
+        println!("AI Renderer: Sending prompt: '{}'", spec.prompt);
+
+        // Генерируем в разрешении, удобном для модели, а потом приводим к размеру виджета.
+        let (width, height) = bucket_size(spec.width, spec.height, self.native_resolution);
         let request_body = Txt2ImgRequest {
-            prompt: prompt.to_string(),
-            negative_prompt: "blurry, worst quality, low quality, deformed, text, watermark, signature".to_string(),
-            steps: 20,
+            prompt: spec.prompt.clone(),
+            negative_prompt: spec.negative_prompt.clone(),
+            steps: spec.steps,
             width,
             height,
-            cfg_scale: 7.0,
-            sampler_name: "Euler a".to_string(),
+            cfg_scale: spec.cfg_scale,
+            sampler_name: spec.sampler_name.clone(),
+            seed: spec.seed,
+        };
+
+        let init = spec.init.as_ref().and_then(|init| init.image.as_ref().map(|image| (image, init.denoising_strength)));
+        let request = match init {
+            Some((image, denoising_strength)) => self.client
+                .post(self.endpoint("/sdapi/v1/img2img"))
+                .json(&Img2ImgRequest {
+                    base: request_body,
+                    init_images: vec![encode_png(&fit_image(image, width, height, FitMode::Stretch, spec.filter))?],
+                    denoising_strength,
+                }),
+            None => self.client
+                .post(self.endpoint("/sdapi/v1/txt2img"))
+                .json(&request_body),
         };
 
-        let response = self.client.post(API_URL).json(&request_body).send()?;
+        let interrupts = self.interrupts.load(Ordering::SeqCst);
+        let response = request
+            .send()
+            .map_err(classify_request_error)?;
 
-        if !response.status().is_success() {
-            return Err(format!("API Error: {}", response.text()?).into());
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().unwrap_or_default();
+            return Err(GenError::Http { status: status.as_u16(), body });
+        }
+        if self.interrupts.load(Ordering::SeqCst) != interrupts {
+            return Err(GenError::Interrupted);
         }
 
-        let response_data: Txt2ImgResponse = response.json()?;
-
-        let base64_image = response_data.images.get(0).ok_or("No images in API response")?;
-        let image_bytes = general_purpose::STANDARD.decode(base64_image)?;
+        let response_data: Txt2ImgResponse = response.json().map_err(classify_request_error)?;
 
-        let image = image::load_from_memory_with_format(&image_bytes, ImageFormat::Png)?;
+        let base64_image = response_data.images.first().ok_or(GenError::NoImages)?;
+        let image = fit_image(&decode_png(base64_image)?, spec.width, spec.height, spec.fit, spec.filter);
+        let info: Txt2ImgInfo = serde_json::from_str(&response_data.info).unwrap_or_default();
 
         println!("AI Renderer: Image received successfully!");
-        Ok(image)
+        Ok(GeneratedImage {
+            image,
+            info: GenInfo { seed: info.seed, model_hash: info.sd_model_hash },
+        })
+    }
+
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+}
+
+impl Default for AiRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ImageBackend for AiRenderer {
+    fn name(&self) -> &str {
+        "a1111"
+    }
+
+    fn supports_img2img(&self) -> bool {
+        true
+    }
+
+    /// Сервер выполняет генерации по одной, а `/sdapi/v1/progress` сообщает только о текущей.
+    /// Второй запрос лишь ждал бы в очереди сервера, расходуя свой таймаут, и показывал бы чужой прогресс.
+    fn max_concurrency(&self) -> usize {
+        1
+    }
+
+    fn model_id(&self) -> String {
+        let model = self.model.get_or_init(|| {
+            self.client
+                .get(self.endpoint("/sdapi/v1/options"))
+                .timeout(Duration::from_secs(5))
+                .send()
+                .and_then(|response| response.json::<OptionsResponse>())
+                .map(|options| options.sd_model_checkpoint)
+                .unwrap_or_else(|_| "unknown".to_string())
+        });
+        // Разрешение генерации влияет на картинку, поэтому входит в идентификатор.
+        format!("a1111:{}@{}", model, self.native_resolution)
+    }
+
+    fn generate_image(&self, spec: &AssetSpec) -> Result<DynamicImage, GenError> {
+        self.run_generation(spec, &CancelToken::new()).map(|generated| generated.image)
     }
-}
\ No newline at end of file
+
+    fn generate_with_progress(
+        &self,
+        spec: &AssetSpec,
+        cancel: &CancelToken,
+        on_progress: &mut dyn FnMut(GenProgress),
+    ) -> Result<GeneratedImage, GenError> {
+        // Запрос генерации блокирующий, поэтому выполняем его в отдельном потоке,
+        // а в текущем опрашиваем прогресс, пока он не завершится.
+        thread::scope(|scope| {
+            let generation = scope.spawn(|| self.run_generation(spec, cancel));
+            on_progress(GenProgress::default());
+
+            let mut last_poll = Instant::now();
+            while !generation.is_finished() {
+                thread::sleep(Duration::from_millis(20));
+                if last_poll.elapsed() >= PROGRESS_POLL_INTERVAL {
+                    last_poll = Instant::now();
+                    if let Some(progress) = self.fetch_progress(spec) {
+                        on_progress(progress);
+                    }
+                }
+            }
+
+            generation
+                .join()
+                .unwrap_or_else(|_| Err(GenError::Backend("generation thread panicked".to_string())))
+        })
+    }
+
+    fn interrupt(&self) {
+        self.interrupts.fetch_add(1, Ordering::SeqCst);
+        // Ошибку игнорируем: если сервер недоступен, прерывать нечего.
+        let _ = self.client
+            .post(self.endpoint("/sdapi/v1/interrupt"))
+            .timeout(Duration::from_secs(2))
+            .send();
+    }
+}
+
+/// Декодирует PNG, пришедший от API в Base64.
+fn decode_png(base64_image: &str) -> Result<DynamicImage, GenError> {
+    let image_bytes = general_purpose::STANDARD.decode(base64_image).map_err(|e| GenError::ImageDecode(e.to_string()))?;
+    image::load_from_memory_with_format(&image_bytes, ImageFormat::Png).map_err(|e| GenError::ImageDecode(e.to_string()))
+}
+
+/// Кодирует картинку в PNG и Base64 для отправки в API.
+fn encode_png(image: &DynamicImage) -> Result<String, GenError> {
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    image.write_to(&mut bytes, ImageFormat::Png).map_err(|e| GenError::Backend(e.to_string()))?;
+    Ok(general_purpose::STANDARD.encode(bytes.into_inner()))
+}
+
+fn build_client(connect_timeout: Duration, request_timeout: Duration) -> Client {
+    Client::builder()
+        .connect_timeout(connect_timeout)
+        .timeout(request_timeout)
+        .build()
+        .expect("failed to build HTTP client")
+}
+
+/// Раскладывает ошибку reqwest по видам `GenError`.
+fn classify_request_error(e: reqwest::Error) -> GenError {
+    // Таймаут соединения — это ошибка соединения: запрос до сервера не дошел, и его можно повторить
+    if e.is_connect() {
+        GenError::Connect(e.to_string())
+    } else if e.is_timeout() {
+        GenError::Timeout(e.to_string())
+    } else if e.is_decode() {
+        GenError::MalformedResponse(e.to_string())
+    } else {
+        GenError::Backend(e.to_string())
+    }
+}
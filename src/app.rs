@@ -0,0 +1,48 @@
+// src/app.rs
+
+use crate::clock::Clock;
+use crate::ui::action::Actions;
+use crate::ui::widget::UiEvent;
+use crate::ui::AppUi;
+use crate::AppState;
+
+/// Действие кнопки Submit на главном экране.
+pub const ACTION_SUBMIT: &str = "submit";
+/// Действие кнопки Clear на главном экране.
+pub const ACTION_CLEAR: &str = "clear";
+
+/// Обработчики действий главного экрана.
+pub fn main_screen_actions() -> Actions {
+    Actions::new()
+        .on(ACTION_SUBMIT, |app_state, _ui| {
+            if app_state.text_input_content == "shadowin" {
+                app_state.message = "Welcome, master.".to_string();
+                app_state.bg_color = [40, 20, 20, 255];
+            } else {
+                app_state.message = format!("Submitted: {}", app_state.text_input_content);
+            }
+        })
+        .on(ACTION_CLEAR, |app_state: &mut AppState, _ui| {
+            // Поле ввода привязано к `text_input_content` и очистится вместе с ним
+            app_state.text_input_content.clear();
+            app_state.message = "Cleared.".to_string();
+            app_state.bg_color = [20, 20, 30, 255];
+            app_state.click_count = 0;
+        })
+}
+
+/// Один кадр работающего UI: обновление виджетов, событие ввода (если есть) и вызванные
+/// им действия. Привязанные виджеты синхронизируются с `AppState` до действий и после них.
+/// Общий для цикла событий в `main.rs` и для `InputDriver`.
+pub fn run_frame(app_state: &mut AppState, ui: &mut AppUi, actions: &mut Actions, event: Option<&UiEvent>, clock: &dyn Clock) {
+    ui.update(app_state, clock);
+    let fired = event.map(|event| ui.handle_event(app_state, event)).unwrap_or_default();
+    ui.read_bindings(app_state);
+    for action in fired {
+        app_state.click_count += 1;
+        if !actions.dispatch(&action, app_state, ui) {
+            println!("No handler registered for action '{}'.", action);
+        }
+    }
+    ui.write_bindings(app_state);
+}
@@ -0,0 +1,117 @@
+// src/backend/fit.rs
+
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImage, RgbaImage};
+use serde::Serialize;
+use std::str::FromStr;
+
+/// Размеры, кратные которым лучше всего понимает Stable Diffusion.
+pub const SIZE_MULTIPLE: u32 = 64;
+
+/// Как вписать сгенерированную картинку в точный размер виджета.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Default)]
+pub enum FitMode {
+    /// Заполнить виджет целиком, обрезав лишнее по краям.
+    #[default]
+    Cover,
+    /// Вписать картинку целиком, оставив прозрачные поля.
+    Contain,
+    /// Растянуть без сохранения пропорций.
+    Stretch,
+}
+
+impl FromStr for FitMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cover" => Ok(FitMode::Cover),
+            "contain" => Ok(FitMode::Contain),
+            "stretch" => Ok(FitMode::Stretch),
+            other => Err(format!("unknown fit mode: '{}' (expected cover, contain or stretch)", other)),
+        }
+    }
+}
+
+/// Фильтр масштабирования.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Default)]
+pub enum ResizeFilter {
+    Nearest,
+    Linear,
+    Cubic,
+    #[default]
+    Lanczos,
+}
+
+impl ResizeFilter {
+    fn filter_type(self) -> FilterType {
+        match self {
+            ResizeFilter::Nearest => FilterType::Nearest,
+            ResizeFilter::Linear => FilterType::Triangle,
+            ResizeFilter::Cubic => FilterType::CatmullRom,
+            ResizeFilter::Lanczos => FilterType::Lanczos3,
+        }
+    }
+}
+
+impl FromStr for ResizeFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nearest" => Ok(ResizeFilter::Nearest),
+            "linear" => Ok(ResizeFilter::Linear),
+            "cubic" => Ok(ResizeFilter::Cubic),
+            "lanczos" => Ok(ResizeFilter::Lanczos),
+            other => Err(format!("unknown filter: '{}' (expected nearest, linear, cubic or lanczos)", other)),
+        }
+    }
+}
+
+/// Подбирает разрешение генерации для виджета `width x height`: пропорции сохраняются,
+/// площадь близка к `native x native`, стороны кратны 64 и не больше `2 * native`.
+pub fn bucket_size(width: u32, height: u32, native: u32) -> (u32, u32) {
+    let aspect = width.max(1) as f32 / height.max(1) as f32;
+    let area = (native * native) as f32;
+    let max_side = (native * 2) as f32;
+
+    let mut w = (area * aspect).sqrt();
+    let mut h = w / aspect;
+    if w > max_side {
+        w = max_side;
+        h = w / aspect;
+    }
+    if h > max_side {
+        h = max_side;
+        w = h * aspect;
+    }
+
+    (round_to_multiple(w), round_to_multiple(h))
+}
+
+fn round_to_multiple(side: f32) -> u32 {
+    ((side / SIZE_MULTIPLE as f32).round() as u32).max(1) * SIZE_MULTIPLE
+}
+
+/// Приводит картинку к точному размеру `width x height`.
+pub fn fit_image(image: &DynamicImage, width: u32, height: u32, mode: FitMode, filter: ResizeFilter) -> DynamicImage {
+    if image.width() == width && image.height() == height {
+        return image.clone();
+    }
+    let filter = filter.filter_type();
+
+    match mode {
+        FitMode::Stretch => image.resize_exact(width, height, filter),
+        FitMode::Cover => image.resize_to_fill(width, height, filter),
+        FitMode::Contain => {
+            let scaled = image.resize(width, height, filter);
+            let mut canvas = DynamicImage::ImageRgba8(RgbaImage::new(width, height));
+            let x = (width - scaled.width()) / 2;
+            let y = (height - scaled.height()) / 2;
+            canvas
+                .copy_from(&scaled.to_rgba8(), x, y)
+                .expect("scaled image always fits into the canvas");
+            canvas
+        }
+    }
+}
@@ -0,0 +1,60 @@
+// src/backend/memory.rs
+
+use super::{fnv1a, AssetSpec, GenError, ImageBackend};
+use image::{DynamicImage, Rgba, RgbaImage};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Детерминированный бэкенд без сети: отдает заранее заданные картинки,
+/// а для остальных ключей заливает ассет цветом, вычисленным из промпта.
+/// Нужен для тестов и для запуска без генератора.
+pub struct MemoryBackend {
+    images: HashMap<String, DynamicImage>,
+    requests: Mutex<Vec<AssetSpec>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self { images: HashMap::new(), requests: Mutex::new(Vec::new()) }
+    }
+
+    /// Регистрирует готовую картинку для логического ключа ассета.
+    pub fn with_image(mut self, key: &str, image: DynamicImage) -> Self {
+        self.images.insert(key.to_string(), image);
+        self
+    }
+
+    /// Все запросы, которые получил бэкенд, в порядке поступления.
+    pub fn requests(&self) -> Vec<AssetSpec> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Default for MemoryBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ImageBackend for MemoryBackend {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    fn generate_image(&self, spec: &AssetSpec) -> Result<DynamicImage, GenError> {
+        self.requests.lock().unwrap().push(spec.clone());
+
+        if let Some(image) = self.images.get(&spec.key) {
+            return Ok(image.clone());
+        }
+
+        let color = color_from_prompt(&spec.prompt);
+        Ok(DynamicImage::ImageRgba8(RgbaImage::from_pixel(spec.width, spec.height, Rgba(color))))
+    }
+}
+
+/// Стабильный цвет из строки, чтобы одинаковые промпты давали одинаковые картинки.
+fn color_from_prompt(prompt: &str) -> [u8; 4] {
+    let [r, g, b, _] = fnv1a(prompt).to_le_bytes();
+    [r, g, b, 255]
+}
@@ -0,0 +1,288 @@
+// src/backend/mod.rs
+
+pub mod fit;
+pub mod memory;
+pub mod procedural;
+pub mod retry;
+
+use crate::ui::skin::Insets;
+use crate::ui::widgets::VisualState;
+use fit::{FitMode, ResizeFilter};
+use image::DynamicImage;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Негативный промпт, который используется, если виджет не задал свой.
+pub const DEFAULT_NEGATIVE_PROMPT: &str = "blurry, worst quality, low quality, deformed, text, watermark, signature";
+
+/// Параметры сэмплера по умолчанию.
+pub const DEFAULT_STEPS: u32 = 20;
+pub const DEFAULT_CFG_SCALE: f32 = 7.0;
+pub const DEFAULT_SAMPLER: &str = "Euler a";
+
+/// Насколько сильно img2img меняет исходную картинку по умолчанию.
+pub const DEFAULT_DENOISING_STRENGTH: f32 = 0.45;
+
+/// Исходная картинка для img2img.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InitImage {
+    /// Контентный хэш исходного ассета: входит в ключ кэша производного.
+    pub source_hash: String,
+    /// 0.0 — картинка не меняется, 1.0 — рисуется заново.
+    pub denoising_strength: f32,
+    /// Сама картинка; появляется, когда исходный ассет готов.
+    pub image: Option<Arc<DynamicImage>>,
+}
+
+/// Полное описание одного ассета, который нужно получить от генератора.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssetSpec {
+    /// Логический ключ ассета в кэше, например `0-Hovered`.
+    pub key: String,
+    /// Виджет, которому принадлежит ассет. Состояния одного виджета могут делить сид.
+    pub widget: String,
+    pub prompt: String,
+    pub negative_prompt: String,
+    pub width: u32,
+    pub height: u32,
+    pub sampler_name: String,
+    pub steps: u32,
+    pub cfg_scale: f32,
+    /// Сид генерации; `-1` — случайный.
+    pub seed: i64,
+    /// Как привести картинку, сгенерированную в удобном для модели разрешении, к размеру виджета.
+    pub fit: FitMode,
+    pub filter: ResizeFilter,
+    /// Визуальное состояние виджета, для которого рисуется фон.
+    pub state: VisualState,
+    /// Края nine-slice картинки; `None` — картинка рисуется ровно в размер виджета.
+    pub nine_slice: Option<Insets>,
+    /// Ключ ассета, из которого этот можно вывести через img2img.
+    pub derive_from: Option<String>,
+    /// Исходная картинка, если ассет действительно выводится через img2img.
+    pub init: Option<InitImage>,
+}
+
+impl AssetSpec {
+    pub fn new(key: String, prompt: String, width: u32, height: u32) -> Self {
+        Self {
+            widget: key.clone(),
+            key,
+            prompt,
+            negative_prompt: DEFAULT_NEGATIVE_PROMPT.to_string(),
+            width,
+            height,
+            sampler_name: DEFAULT_SAMPLER.to_string(),
+            steps: DEFAULT_STEPS,
+            cfg_scale: DEFAULT_CFG_SCALE,
+            seed: -1,
+            fit: FitMode::default(),
+            filter: ResizeFilter::default(),
+            state: VisualState::Idle,
+            nine_slice: None,
+            derive_from: None,
+            init: None,
+        }
+    }
+
+    pub fn with_state(mut self, state: VisualState) -> Self {
+        self.state = state;
+        self
+    }
+
+    pub fn with_widget(mut self, widget: String) -> Self {
+        self.widget = widget;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: i64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Помечает ассет как nine-slice картинку с краями `insets`.
+    pub fn with_nine_slice(mut self, insets: Insets) -> Self {
+        self.nine_slice = Some(insets);
+        self
+    }
+
+    /// Помечает ассет как вариацию другого (например, Hovered из Idle).
+    pub fn derived_from(mut self, source_key: String) -> Self {
+        self.derive_from = Some(source_key);
+        self
+    }
+}
+
+/// Промежуточное состояние одной генерации.
+#[derive(Clone, Debug, Default)]
+pub struct GenProgress {
+    /// Доля выполненной работы, от 0.0 до 1.0.
+    pub fraction: f32,
+    /// Оценка оставшегося времени, если генератор ее сообщает.
+    pub eta: Option<Duration>,
+    /// Частично готовая картинка, если генератор ее отдает.
+    pub preview: Option<Arc<DynamicImage>>,
+}
+
+/// Сведения о генерации, которые сообщил сам генератор.
+#[derive(Clone, Debug, Default)]
+pub struct GenInfo {
+    /// Сид, с которым картинка реально сгенерирована (важно, если запрашивался случайный).
+    pub seed: Option<i64>,
+    /// Хэш модели на стороне генератора.
+    pub model_hash: Option<String>,
+}
+
+/// Флаг отмены одной группы генераций, обычно одного пула. Клоны делят флаг,
+/// а новый пул получает свой, поэтому отмена старого не мешает повторам в новом.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Готовая картинка вместе со сведениями о том, как она получена.
+pub struct GeneratedImage {
+    pub image: DynamicImage,
+    pub info: GenInfo,
+}
+
+/// Ошибка генерации изображения.
+#[derive(Debug)]
+pub enum GenError {
+    /// Сервер недоступен: соединение отклонено или адрес не найден.
+    Connect(String),
+    /// Сервер не уложился в таймаут.
+    Timeout(String),
+    /// Сервер ответил HTTP-ошибкой.
+    Http { status: u16, body: String },
+    /// Тело ответа — не тот JSON, который мы ждали.
+    MalformedResponse(String),
+    /// В ответе нет ни одной картинки.
+    NoImages,
+    /// Картинку из ответа не удалось декодировать (Base64 или PNG).
+    ImageDecode(String),
+    /// Генерацию прервали, пока она шла: картинка в ответе недорисована.
+    Interrupted,
+    /// Прочие ошибки бэкенда.
+    Backend(String),
+}
+
+impl GenError {
+    /// Имеет ли смысл повторить запрос: сервер мог быть занят или еще запускаться.
+    /// Таймаут ответа не повторяем: сервер продолжает исходную генерацию,
+    /// и повтор встал бы в его очередь дубликатом.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            GenError::Connect(_) => true,
+            GenError::Http { status, .. } => *status == 429 || *status >= 500,
+            _ => false,
+        }
+    }
+
+    /// Что пользователю сделать, чтобы ошибка ушла.
+    pub fn hint(&self) -> &'static str {
+        match self {
+            GenError::Connect(_) => "Stable Diffusion is not reachable. Start webui with --api, or run with --offline.",
+            GenError::Timeout(_) => "The server is too slow. Raise --timeout or lower the number of steps.",
+            GenError::Http { status: 404, .. } => "API endpoint not found. Start webui with the --api flag.",
+            GenError::Http { status: 422, .. } => "The server rejected the request parameters. Check the webui console.",
+            GenError::Http { .. } => "The server returned an error. Check the webui console.",
+            GenError::MalformedResponse(_) => "Unexpected API response. Is this an AUTOMATIC1111 server?",
+            GenError::NoImages => "The server returned no images. Check the webui console.",
+            GenError::ImageDecode(_) => "The server returned an unreadable image.",
+            GenError::Interrupted => "Generation was interrupted before it finished.",
+            GenError::Backend(_) => "The image backend failed. See the log for details.",
+        }
+    }
+}
+
+impl fmt::Display for GenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenError::Connect(msg) => write!(f, "connection failed: {}", msg),
+            GenError::Timeout(msg) => write!(f, "timed out: {}", msg),
+            GenError::Http { status, body } => write!(f, "HTTP {}: {}", status, body),
+            GenError::MalformedResponse(msg) => write!(f, "malformed response: {}", msg),
+            GenError::NoImages => write!(f, "no images in API response"),
+            GenError::ImageDecode(msg) => write!(f, "image decode failed: {}", msg),
+            GenError::Interrupted => write!(f, "generation was interrupted"),
+            GenError::Backend(msg) => write!(f, "backend error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for GenError {}
+
+/// Источник фонов для виджетов. `LoadingState` и `AppUi` знают только об этом трейте,
+/// поэтому новый генератор добавляется без правок экрана загрузки и виджетов.
+pub trait ImageBackend: Send + Sync {
+    /// Короткое имя бэкенда для логов.
+    fn name(&self) -> &str;
+
+    /// Идентификатор бэкенда и модели, которая рисует картинки.
+    /// Входит в ключ кэша: смена модели означает перегенерацию.
+    fn model_id(&self) -> String {
+        self.name().to_string()
+    }
+
+    /// Стоит ли сохранять результаты на диск. Мгновенным генераторам кэш не нужен.
+    fn is_cacheable(&self) -> bool {
+        true
+    }
+
+    /// Сколько генераций бэкенд действительно выполняет одновременно.
+    /// Пул не запускает больше рабочих потоков, даже если попросили.
+    fn max_concurrency(&self) -> usize {
+        usize::MAX
+    }
+
+    /// Умеет ли бэкенд выводить картинку из исходной (img2img).
+    /// Остальные бэкенды игнорируют `AssetSpec::init` и рисуют с нуля.
+    fn supports_img2img(&self) -> bool {
+        false
+    }
+
+    /// Генерирует картинку строго по описанию ассета. Вызов блокирующий.
+    fn generate_image(&self, spec: &AssetSpec) -> Result<DynamicImage, GenError>;
+
+    /// То же, что `generate_image`, но по ходу работы сообщает о прогрессе
+    /// и возвращает сведения о генерации. По умолчанию прогресса и сведений нет.
+    /// После `cancel` бэкенд не должен начинать повторы и новые запросы.
+    fn generate_with_progress(
+        &self,
+        spec: &AssetSpec,
+        cancel: &CancelToken,
+        on_progress: &mut dyn FnMut(GenProgress),
+    ) -> Result<GeneratedImage, GenError> {
+        let _ = (cancel, on_progress);
+        let image = self.generate_image(spec)?;
+        Ok(GeneratedImage { image, info: GenInfo::default() })
+    }
+
+    /// Просит прервать текущие генерации. Вызывается из другого потока при отмене.
+    fn interrupt(&self) {}
+}
+
+/// Стабильный 32-битный FNV-1a хэш строки: одинаковый на всех машинах и версиях Rust.
+pub(crate) fn fnv1a(s: &str) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in s.bytes() {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    hash
+}
@@ -0,0 +1,136 @@
+// src/backend/retry.rs
+
+use super::GenError;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Паузу между попытками спим кусками такой длины, чтобы отмена не ждала ее целиком.
+const SLEEP_SLICE: Duration = Duration::from_millis(50);
+
+/// Политика повторов с экспоненциальной задержкой для временных ошибок.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// Сколько всего попыток, включая первую.
+    pub max_attempts: u32,
+    /// Пауза перед второй попыткой.
+    pub initial_backoff: Duration,
+    /// Потолок паузы между попытками.
+    pub max_backoff: Duration,
+    /// Во сколько раз растет пауза после каждой попытки.
+    pub multiplier: f32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+            multiplier: 2.0,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Без повторов: ошибка возвращается сразу.
+    pub fn none() -> Self {
+        Self { max_attempts: 1, ..Self::default() }
+    }
+
+    /// Пауза перед попыткой номер `attempt + 1` (нумерация с единицы).
+    /// Если пауза не помещается в `Duration` или множитель не конечен, берется `max_backoff`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let secs = self.initial_backoff.as_secs_f64() * (self.multiplier as f64).powi(exponent);
+        Duration::try_from_secs_f64(secs).map_or(self.max_backoff, |delay| delay.min(self.max_backoff))
+    }
+
+    /// Выполняет `operation`, повторяя ее при временных ошибках.
+    /// `should_stop` проверяется перед паузой и во время нее; если отмена пришла,
+    /// повтора не будет и вернется `GenError::Interrupted`.
+    pub fn run<T>(
+        &self,
+        should_stop: impl Fn() -> bool,
+        mut operation: impl FnMut() -> Result<T, GenError>,
+    ) -> Result<T, GenError> {
+        let mut attempt = 1;
+        loop {
+            match operation() {
+                Err(e) if e.is_transient() && attempt < self.max_attempts && !should_stop() => {
+                    let delay = self.backoff(attempt);
+                    println!("Transient error ({}), retrying in {:.1}s...", e, delay.as_secs_f32());
+                    if !sleep_unless(delay, &should_stop) {
+                        return Err(GenError::Interrupted);
+                    }
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Спит `delay`, просыпаясь каждые `SLEEP_SLICE`. Возвращает `false`, если `should_stop` сработал.
+fn sleep_unless(delay: Duration, should_stop: &impl Fn() -> bool) -> bool {
+    let deadline = Instant::now() + delay;
+    loop {
+        if should_stop() {
+            return false;
+        }
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return true;
+        }
+        thread::sleep(left.min(SLEEP_SLICE));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn backoff_grows_up_to_the_cap() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_secs(1));
+        assert_eq!(policy.backoff(4), Duration::from_secs(4));
+        assert_eq!(policy.backoff(6), Duration::from_secs(8));
+    }
+
+    #[test]
+    fn backoff_saturates_instead_of_panicking() {
+        let policy = RetryPolicy { multiplier: 1e30, ..RetryPolicy::default() };
+        assert_eq!(policy.backoff(u32::MAX), policy.max_backoff);
+
+        for multiplier in [f32::INFINITY, f32::NAN, -2.0] {
+            let policy = RetryPolicy { multiplier, ..RetryPolicy::default() };
+            assert_eq!(policy.backoff(2), policy.max_backoff);
+        }
+    }
+
+    #[test]
+    fn cancel_during_backoff_skips_the_retry() {
+        let policy = RetryPolicy { initial_backoff: Duration::from_secs(8), ..RetryPolicy::default() };
+        let started = Instant::now();
+        let checks = Cell::new(0);
+        let mut attempts = 0;
+
+        // Первые две проверки — перед паузой и в ее начале; отмена приходит посреди паузы
+        let result: Result<(), _> = policy.run(
+            || {
+                checks.set(checks.get() + 1);
+                checks.get() > 2
+            },
+            || {
+                attempts += 1;
+                Err(GenError::Connect("refused".to_string()))
+            },
+        );
+
+        assert!(matches!(result, Err(GenError::Interrupted)));
+        assert_eq!(attempts, 1);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,331 @@
+// src/cache.rs
+
+use crate::backend::fit::{FitMode, ResizeFilter};
+use crate::backend::{AssetSpec, GenInfo};
+use crate::ui::skin::Insets;
+use image::{DynamicImage, ImageFormat};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Папка кэша по умолчанию.
+pub const DEFAULT_CACHE_DIR: &str = "cache";
+
+const INDEX_FILE: &str = "index.json";
+
+/// Все параметры, от которых зависит картинка. Хэш от них и есть имя файла в кэше.
+#[derive(Serialize)]
+struct CacheKeyParams<'a> {
+    prompt: &'a str,
+    negative_prompt: &'a str,
+    width: u32,
+    height: u32,
+    sampler_name: &'a str,
+    steps: u32,
+    cfg_scale: f32,
+    seed: i64,
+    fit: FitMode,
+    filter: ResizeFilter,
+    model: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    init_source: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    denoising_strength: Option<f32>,
+}
+
+/// Происхождение ассета: все, что нужно, чтобы понять, откуда взялась картинка,
+/// и воспроизвести ее. Лежит рядом с PNG в файле `{hash}.json`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AssetMetadata {
+    pub key: String,
+    pub prompt: String,
+    pub negative_prompt: String,
+    pub width: u32,
+    pub height: u32,
+    /// Края nine-slice картинки, если ассет масштабируемый.
+    #[serde(default)]
+    pub insets: Option<Insets>,
+    pub sampler_name: String,
+    pub steps: u32,
+    pub cfg_scale: f32,
+    /// Сид из запроса (`-1` — случайный).
+    pub requested_seed: i64,
+    /// Сид, который сообщил генератор; с ним картинку можно повторить.
+    pub seed: Option<i64>,
+    /// Модель в терминах `ImageBackend::model_id`.
+    pub model: String,
+    pub model_hash: Option<String>,
+    pub backend: String,
+    /// Ключ исходного ассета, если картинка выведена через img2img.
+    #[serde(default)]
+    pub init_source: Option<String>,
+    #[serde(default)]
+    pub denoising_strength: Option<f32>,
+    pub generation_ms: u64,
+    /// Время создания, секунды Unix.
+    pub created_at: u64,
+}
+
+impl AssetMetadata {
+    pub fn new(spec: &AssetSpec, backend: &str, model: &str, info: &GenInfo, elapsed: Duration) -> Self {
+        Self {
+            key: spec.key.clone(),
+            prompt: spec.prompt.clone(),
+            negative_prompt: spec.negative_prompt.clone(),
+            width: spec.width,
+            height: spec.height,
+            insets: spec.nine_slice,
+            sampler_name: spec.sampler_name.clone(),
+            steps: spec.steps,
+            cfg_scale: spec.cfg_scale,
+            requested_seed: spec.seed,
+            seed: info.seed,
+            model: model.to_string(),
+            model_hash: info.model_hash.clone(),
+            backend: backend.to_string(),
+            init_source: spec.init.as_ref().and(spec.derive_from.clone()),
+            denoising_strength: spec.init.as_ref().map(|init| init.denoising_strength),
+            generation_ms: elapsed.as_millis() as u64,
+            created_at: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+        }
+    }
+}
+
+/// Индекс кэша: логический ключ (`0-Hovered`) -> контентный хэш.
+#[derive(Serialize, Deserialize, Default)]
+struct CacheIndex {
+    entries: BTreeMap<String, String>,
+}
+
+/// Контентно-адресуемый кэш ассетов на диске.
+/// Файл называется по хэшу параметров генерации, поэтому правка промпта,
+/// размера или модели автоматически приводит к перегенерации.
+pub struct AssetCache {
+    dir: PathBuf,
+    index: CacheIndex,
+}
+
+impl AssetCache {
+    /// Открывает кэш в папке `dir`, создавая ее при необходимости.
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        // Поврежденный индекс не фатален: он восстановится по мере загрузки ассетов.
+        let index = fs::read(dir.join(INDEX_FILE))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+
+        Ok(Self { dir, index })
+    }
+
+    /// Хэш параметров генерации ассета для модели `model`.
+    pub fn content_hash(spec: &AssetSpec, model: &str) -> String {
+        let params = CacheKeyParams {
+            prompt: &spec.prompt,
+            negative_prompt: &spec.negative_prompt,
+            width: spec.width,
+            height: spec.height,
+            sampler_name: &spec.sampler_name,
+            steps: spec.steps,
+            cfg_scale: spec.cfg_scale,
+            seed: spec.seed,
+            fit: spec.fit,
+            filter: spec.filter,
+            model,
+            init_source: spec.init.as_ref().map(|init| init.source_hash.as_str()),
+            denoising_strength: spec.init.as_ref().map(|init| init.denoising_strength),
+        };
+        let canonical = serde_json::to_vec(&params).expect("cache key params are always serializable");
+        let digest = Sha256::digest(&canonical);
+        digest[..16].iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Хэш, на который сейчас указывает логический ключ.
+    pub fn lookup(&self, key: &str) -> Option<&str> {
+        self.index.entries.get(key).map(String::as_str)
+    }
+
+    /// Загружает ассет, если в кэше есть картинка ровно с такими параметрами.
+    pub fn load(&mut self, spec: &AssetSpec, model: &str) -> Option<DynamicImage> {
+        let hash = Self::content_hash(spec, model);
+        let bytes = fs::read(self.image_path(&hash)).ok()?;
+        let image = image::load_from_memory_with_format(&bytes, ImageFormat::Png).ok()?;
+
+        if self.lookup(&spec.key) != Some(hash.as_str()) {
+            self.index.entries.insert(spec.key.clone(), hash);
+            self.save_index();
+        }
+        Some(image)
+    }
+
+    /// Сохраняет картинку и ее метаданные под хэшем параметров и обновляет индекс.
+    pub fn store(&mut self, spec: &AssetSpec, model: &str, image: &DynamicImage, metadata: &AssetMetadata) -> io::Result<()> {
+        let hash = Self::content_hash(spec, model);
+        image
+            .save_with_format(self.image_path(&hash), ImageFormat::Png)
+            .map_err(io::Error::other)?;
+        let sidecar = serde_json::to_vec_pretty(metadata).map_err(io::Error::other)?;
+        fs::write(self.metadata_path(&hash), sidecar)?;
+
+        self.index.entries.insert(spec.key.clone(), hash);
+        self.save_index();
+        Ok(())
+    }
+
+    /// Метаданные ассета, на который сейчас указывает логический ключ.
+    pub fn metadata(&self, key: &str) -> Option<AssetMetadata> {
+        let hash = self.lookup(key)?;
+        let bytes = fs::read(self.metadata_path(hash)).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    /// Файлы кэша, на которые не ссылается ни один из `live_keys`:
+    /// старые версии ассетов и ассеты удаленных виджетов.
+    pub fn stale_files(&self, live_keys: &[String]) -> Vec<PathBuf> {
+        let live_hashes: HashSet<&str> = live_keys.iter().filter_map(|key| self.lookup(key)).collect();
+
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.is_file() && path.file_name().is_some_and(|name| name != INDEX_FILE))
+            .filter(|path| {
+                let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+                !live_hashes.contains(stem)
+            })
+            .collect()
+    }
+
+    /// Удаляет устаревшие файлы и забывает ключи, которых нет в `live_keys`.
+    /// Возвращает число удаленных файлов.
+    pub fn collect_garbage(&mut self, live_keys: &[String]) -> io::Result<usize> {
+        let stale = self.stale_files(live_keys);
+        for path in &stale {
+            fs::remove_file(path)?;
+        }
+
+        self.index.entries.retain(|key, _| live_keys.contains(key));
+        self.save_index();
+        Ok(stale.len())
+    }
+
+    fn image_path(&self, hash: &str) -> PathBuf {
+        self.dir.join(format!("{}.png", hash))
+    }
+
+    fn metadata_path(&self, hash: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", hash))
+    }
+
+    fn save_index(&self) {
+        let result = serde_json::to_vec_pretty(&self.index)
+            .map_err(io::Error::other)
+            .and_then(|bytes| fs::write(self.dir.join(INDEX_FILE), bytes));
+        if let Err(e) = result {
+            println!("Failed to write cache index: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    const MODEL: &str = "memory";
+
+    /// Временная папка кэша, которая удаляется вместе с тестом.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("shadowin-cache-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn spec(key: &str, prompt: &str) -> AssetSpec {
+        AssetSpec::new(key.to_string(), prompt.to_string(), 8, 4)
+    }
+
+    fn store(cache: &mut AssetCache, spec: &AssetSpec) {
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(spec.width, spec.height));
+        let metadata = AssetMetadata::new(spec, "memory", MODEL, &GenInfo::default(), Duration::ZERO);
+        cache.store(spec, MODEL, &image, &metadata).unwrap();
+    }
+
+    #[test]
+    fn hash_depends_on_every_generation_parameter() {
+        let base = spec("0-Idle", "stone button");
+        let hash = AssetCache::content_hash(&base, MODEL);
+        assert_eq!(hash, AssetCache::content_hash(&base, MODEL));
+        // Ключ и виджет — не параметры генерации: под другим именем та же картинка.
+        assert_eq!(hash, AssetCache::content_hash(&spec("1-Idle", "stone button"), MODEL));
+
+        let mut resized = base.clone();
+        resized.height = 5;
+        let changed = [
+            AssetCache::content_hash(&spec("0-Idle", "wooden button"), MODEL),
+            AssetCache::content_hash(&resized, MODEL),
+            AssetCache::content_hash(&base.clone().with_seed(42), MODEL),
+            AssetCache::content_hash(&base, "sd_xl_base"),
+        ];
+        for other in changed {
+            assert_ne!(hash, other);
+        }
+    }
+
+    #[test]
+    fn load_restores_a_lost_index_entry() {
+        let dir = TempDir::new("index");
+        let spec = spec("0-Idle", "stone button");
+        let hash = AssetCache::content_hash(&spec, MODEL);
+        store(&mut AssetCache::open(&dir.0).unwrap(), &spec);
+
+        fs::remove_file(dir.0.join(INDEX_FILE)).unwrap();
+        let mut cache = AssetCache::open(&dir.0).unwrap();
+        assert_eq!(cache.lookup("0-Idle"), None);
+
+        assert!(cache.load(&spec, MODEL).is_some());
+        assert_eq!(cache.lookup("0-Idle"), Some(hash.as_str()));
+        assert_eq!(AssetCache::open(&dir.0).unwrap().lookup("0-Idle"), Some(hash.as_str()));
+        assert_eq!(cache.metadata("0-Idle").map(|metadata| metadata.prompt), Some("stone button".to_string()));
+    }
+
+    #[test]
+    fn garbage_is_old_versions_and_dead_keys() {
+        let dir = TempDir::new("gc");
+        let mut cache = AssetCache::open(&dir.0).unwrap();
+        let old = spec("0-Idle", "stone button");
+        let new = spec("0-Idle", "marble button");
+        store(&mut cache, &old);
+        store(&mut cache, &new);
+        store(&mut cache, &spec("1-Idle", "wooden button"));
+
+        let live = vec!["0-Idle".to_string()];
+        let new_hash = AssetCache::content_hash(&new, MODEL);
+        let stale = cache.stale_files(&live);
+        assert_eq!(stale.len(), 4);
+        assert!(stale.iter().all(|path| path.file_stem().and_then(|s| s.to_str()) != Some(new_hash.as_str())));
+
+        assert_eq!(cache.collect_garbage(&live).unwrap(), 4);
+        assert!(cache.stale_files(&live).is_empty());
+        assert_eq!(cache.lookup("1-Idle"), None);
+        assert!(cache.load(&new, MODEL).is_some());
+        assert!(cache.load(&old, MODEL).is_none());
+    }
+}
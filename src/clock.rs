@@ -0,0 +1,60 @@
+// src/clock.rs
+
+use std::time::{Duration, Instant};
+
+/// Источник времени для анимаций и мигания курсора. UI читает время только через него,
+/// поэтому кадр можно воспроизвести точно: в тестах, при записи и экспорте видео.
+pub trait Clock {
+    /// Время от начала отсчета часов; смысл имеют только разности.
+    fn now(&self) -> Duration;
+}
+
+/// Настоящее время, отсчитываемое от создания часов.
+#[derive(Clone, Copy, Debug)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// Часы, которые идут только когда их двигают: `advance` на кадр, `set` на точный момент.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ManualClock {
+    now: Duration,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&mut self, duration: Duration) {
+        self.now += duration;
+    }
+
+    pub fn set(&mut self, now: Duration) {
+        self.now = now;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now
+    }
+}
@@ -0,0 +1,137 @@
+// src/driver.rs
+
+use crate::app::run_frame;
+use crate::clock::{Clock, ManualClock};
+use crate::ui::action::Actions;
+use crate::ui::widget::{EditKey, Modifiers, Rect, UiEvent};
+use crate::ui::AppUi;
+use crate::AppState;
+use image::RgbaImage;
+use std::time::Duration;
+
+/// Длительность одного кадра виртуального времени (60 кадров в секунду).
+pub const FRAME_DURATION: Duration = Duration::from_micros(16_667);
+
+/// Размер кадра по умолчанию, как у окна приложения.
+const DEFAULT_SIZE: (u32, u32) = (800, 600);
+
+/// Сценарный ввод без окна: подает в UI синтетические события мыши, клавиш и текста,
+/// прогоняет кадры и дает проверить `AppState` и отрисованный кадр. Кадр обрабатывается
+/// той же `run_frame`, что и в цикле событий `main.rs`.
+pub struct InputDriver {
+    pub app_state: AppState,
+    pub ui: AppUi,
+    actions: Actions,
+    size: (u32, u32),
+    /// Виртуальное время сценария: идет только в `advance`.
+    clock: ManualClock,
+}
+
+impl InputDriver {
+    pub fn new(ui: AppUi, actions: Actions) -> Self {
+        let mut driver = Self {
+            app_state: AppState::default(),
+            ui,
+            actions,
+            size: DEFAULT_SIZE,
+            clock: ManualClock::new(),
+        };
+        driver.ui.layout(driver.size.0, driver.size.1);
+        driver
+    }
+
+    /// Размер кадра; UI раскладывается под него заново.
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.size = (width, height);
+        self.ui.layout(width, height);
+        self
+    }
+
+    pub fn with_app_state(mut self, app_state: AppState) -> Self {
+        self.app_state = app_state;
+        self
+    }
+
+    pub fn state(&self) -> &AppState {
+        &self.app_state
+    }
+
+    /// Сколько виртуального времени прошло с начала сценария.
+    pub fn elapsed(&self) -> Duration {
+        self.clock.now()
+    }
+
+    /// Часы сценария, например чтобы нарисовать кадр тем же временем вне драйвера.
+    pub fn clock(&self) -> &ManualClock {
+        &self.clock
+    }
+
+    /// Подает одно событие ввода, как будто оно пришло из окна.
+    pub fn send(&mut self, event: UiEvent) -> &mut Self {
+        self.app_state.track_pointer(&event);
+        run_frame(&mut self.app_state, &mut self.ui, &mut self.actions, Some(&event), &self.clock);
+        self
+    }
+
+    pub fn move_to(&mut self, x: i32, y: i32) -> &mut Self {
+        self.send(UiEvent::MouseMoved { x, y })
+    }
+
+    pub fn press(&mut self) -> &mut Self {
+        self.send(UiEvent::MouseDown)
+    }
+
+    pub fn release(&mut self) -> &mut Self {
+        self.send(UiEvent::MouseUp)
+    }
+
+    /// Наводит мышь на точку, нажимает и отпускает левую кнопку.
+    pub fn click_at(&mut self, x: i32, y: i32) -> &mut Self {
+        self.move_to(x, y).press().release()
+    }
+
+    /// Щелкает по центру кнопки с действием `action`.
+    pub fn click_action(&mut self, action: &str) -> &mut Self {
+        let bounds = self.ui.action_bounds(action).unwrap_or_else(|| panic!("no button with action '{}'", action));
+        self.click_center(bounds)
+    }
+
+    /// Щелкает по центру поля ввода, чтобы дать ему фокус.
+    pub fn click_text_input(&mut self) -> &mut Self {
+        let bounds = self.ui.binding_bounds(AppState::BIND_INPUT).expect("the screen has no text input bound to the input field");
+        self.click_center(bounds)
+    }
+
+    /// Подает готовый текст, как после нажатия клавиш или подтверждения в IME.
+    pub fn type_text(&mut self, text: &str) -> &mut Self {
+        self.send(UiEvent::Text(text.to_string()))
+    }
+
+    pub fn key(&mut self, key: EditKey) -> &mut Self {
+        self.key_with(key, Modifiers::default())
+    }
+
+    pub fn key_with(&mut self, key: EditKey, modifiers: Modifiers) -> &mut Self {
+        self.send(UiEvent::Key { key, modifiers })
+    }
+
+    /// Продвигает виртуальное время на `duration`, прогоняя кадр за кадром без ввода.
+    pub fn advance(&mut self, duration: Duration) -> &mut Self {
+        let target = self.clock.now() + duration;
+        while self.clock.now() < target {
+            self.clock.set((self.clock.now() + FRAME_DURATION).min(target));
+            run_frame(&mut self.app_state, &mut self.ui, &mut self.actions, None, &self.clock);
+        }
+        self
+    }
+
+    /// Рисует текущий кадр.
+    pub fn frame(&mut self) -> RgbaImage {
+        self.ui.render(&self.app_state, &self.clock, self.size.0, self.size.1)
+    }
+
+    fn click_center(&mut self, bounds: Rect) -> &mut Self {
+        let center = (bounds.x + bounds.width as i32 / 2, bounds.y + bounds.height as i32 / 2);
+        self.click_at(center.0, center.1)
+    }
+}
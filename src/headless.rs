@@ -0,0 +1,52 @@
+// src/headless.rs
+
+use crate::clock::Clock;
+use crate::ui::widget::UiEvent;
+use crate::ui::AppUi;
+use crate::AppState;
+use image::RgbaImage;
+
+/// Состояние виджетов, в котором снимается экран без окна.
+#[derive(Clone, Debug, Default)]
+pub struct ScreenState {
+    /// Где мышь; `None` — мышь за пределами окна.
+    pub pointer: Option<(i32, i32)>,
+    /// Левая кнопка мыши зажата: кнопка под мышью будет нажатой, а поле ввода получит фокус.
+    pub pressed: bool,
+    /// Текст в поле ввода.
+    pub text: Option<String>,
+    /// Сообщение в панели статуса.
+    pub message: Option<String>,
+}
+
+impl ScreenState {
+    /// Приводит UI и состояние приложения к описанному виду через обычные события ввода.
+    /// Анимации доводятся до конца, поэтому результат не зависит от времени.
+    pub fn apply(&self, ui: &mut AppUi, app_state: &mut AppState, clock: &dyn Clock) {
+        if let Some(text) = &self.text {
+            app_state.text_input_content = text.clone();
+        }
+        if let Some(message) = &self.message {
+            app_state.message = message.clone();
+        }
+        ui.write_bindings(app_state);
+
+        app_state.mouse_pos = self.pointer.unwrap_or((-1, -1));
+        let (x, y) = app_state.mouse_pos;
+        ui.handle_event(app_state, &UiEvent::MouseMoved { x, y });
+        if self.pressed {
+            app_state.mouse_pressed = true;
+            ui.handle_event(app_state, &UiEvent::MouseDown);
+        }
+        ui.update(app_state, clock);
+        ui.settle();
+    }
+}
+
+/// Рисует экран в заданном состоянии в RGBA-буфер `width` x `height`.
+pub fn render_screen(ui: &mut AppUi, app_state: &mut AppState, state: &ScreenState, clock: &dyn Clock, width: u32, height: u32) -> RgbaImage {
+    // Раскладка нужна до событий: от нее зависит, над каким виджетом мышь
+    ui.layout(width, height);
+    state.apply(ui, app_state, clock);
+    ui.render(app_state, clock, width, height)
+}
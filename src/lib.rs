@@ -0,0 +1,72 @@
+// src/lib.rs
+
+// Подключаем наши модули
+pub mod ai_renderer;
+pub mod app;
+pub mod backend;
+pub mod cache;
+pub mod clock;
+pub mod driver;
+pub mod headless;
+pub mod loading;
+pub mod pipeline;
+pub mod seeds;
+pub mod ui;
+
+/// Глобальное состояние, которое хранит данные, нужные для логики приложения.
+pub struct AppState {
+    pub mouse_pos: (i32, i32),
+    pub mouse_pressed: bool,
+    pub message: String,
+    pub bg_color: [u8; 4],
+    pub click_count: u32,
+    pub text_input_content: String,
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self {
+            mouse_pos: (-1, -1),
+            mouse_pressed: false,
+            message: "AI Renderer is initializing...".to_string(),
+            bg_color: [20, 20, 30, 255],
+            click_count: 0,
+            text_input_content: String::new(),
+        }
+    }
+}
+
+impl AppState {
+    /// Текст поля ввода: виджет пишет его в `text_input_content` и показывает оттуда.
+    pub const BIND_INPUT: &'static str = "input";
+    /// Строка статуса из сообщения и счетчика нажатий; только для показа.
+    pub const BIND_STATUS: &'static str = "status";
+    /// Все поля, к которым можно привязать виджет в описании экрана.
+    pub const BINDINGS: [&'static str; 2] = [Self::BIND_INPUT, Self::BIND_STATUS];
+
+    /// Значение поля `binding` в том виде, в каком его показывает виджет.
+    pub fn bound_value(&self, binding: &str) -> Option<String> {
+        match binding {
+            Self::BIND_INPUT => Some(self.text_input_content.clone()),
+            Self::BIND_STATUS => Some(format!("{} (Clicks: {})", self.message, self.click_count)),
+            _ => None,
+        }
+    }
+
+    /// Записывает значение, которое отдал привязанный виджет. Поля только для показа не меняются.
+    pub fn set_bound_value(&mut self, binding: &str, value: String) {
+        if binding == Self::BIND_INPUT {
+            self.text_input_content = value;
+        }
+    }
+
+    /// Запоминает положение и кнопку мыши из события ввода.
+    pub fn track_pointer(&mut self, event: &ui::widget::UiEvent) {
+        match *event {
+            ui::widget::UiEvent::MouseMoved { x, y } => self.mouse_pos = (x, y),
+            ui::widget::UiEvent::MouseDown => self.mouse_pressed = true,
+            ui::widget::UiEvent::MouseUp => self.mouse_pressed = false,
+            _ => {}
+        }
+    }
+}
@@ -1,165 +1,444 @@
 // src/loading.rs
 
-use crate::ai_renderer::AiRenderer;
-// ИСПРАВЛЕНИЕ: Убираем TextInput и TextPanel, так как они не используются здесь
-use crate::ui::widgets::Button;
+use crate::backend::fit::{FitMode, ResizeFilter};
+use crate::backend::{AssetSpec, GenProgress, ImageBackend, InitImage, DEFAULT_DENOISING_STRENGTH};
+use crate::pipeline::{GenerationEvent, GenerationPool};
+use crate::seeds::{SeedLock, SeedPolicy, DEFAULT_SEED_LOCK};
+use crate::ui::canvas::Canvas;
+use crate::ui::widget::Rect;
 use crate::ui::AppUi;
-use image::{DynamicImage, ImageFormat};
+use crate::cache::{AssetCache, AssetMetadata, DEFAULT_CACHE_DIR};
+use crate::clock::Clock;
+use image::{DynamicImage, RgbaImage};
 use rusttype::{point, Font, Scale};
-use std::collections::HashMap;
-use std::fs;
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
+use std::time::Duration;
 
-const CACHE_DIR: &str = "cache";
+/// Сколько ассетов генерируется одновременно, если не задано иначе.
+pub const DEFAULT_CONCURRENCY: usize = 2;
 
-/// Структура, описывающая один ассет, который нужно сгенерировать.
-struct AssetRequest {
-    key: String,
-    prompt: String,
-    width: u32,
-    height: u32,
+/// Настройки фазы загрузки.
+#[derive(Clone, Debug)]
+pub struct LoadingOptions {
+    /// Сколько ассетов генерировать параллельно; не больше, чем выполняет бэкенд.
+    pub concurrency: usize,
+    /// Папка кэша ассетов.
+    pub cache_dir: String,
+    /// Удалить из кэша устаревшие файлы после загрузки.
+    pub collect_garbage: bool,
+    /// Как выбирать сиды генерации.
+    pub seeds: SeedPolicy,
+    /// Куда записывать сиды скина.
+    pub seed_lock: String,
+    /// Выводить Hovered/Pressed из готового Idle через img2img, если бэкенд это умеет.
+    pub derive_states: bool,
+    /// Сила img2img при выводе состояний.
+    pub denoising_strength: f32,
+    /// Как приводить сгенерированные картинки к размеру виджетов.
+    pub fit: FitMode,
+    pub filter: ResizeFilter,
+}
+
+impl Default for LoadingOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: DEFAULT_CONCURRENCY,
+            cache_dir: DEFAULT_CACHE_DIR.to_string(),
+            collect_garbage: false,
+            seeds: SeedPolicy::default(),
+            seed_lock: DEFAULT_SEED_LOCK.to_string(),
+            derive_states: true,
+            denoising_strength: DEFAULT_DENOISING_STRENGTH,
+            fit: FitMode::default(),
+            filter: ResizeFilter::default(),
+        }
+    }
 }
 
 /// Состояние экрана загрузки.
 pub struct LoadingState {
     font: Arc<Font<'static>>,
-    ai_renderer: Arc<AiRenderer>,
+    backend: Arc<dyn ImageBackend>,
+    /// UI, для которого готовятся ассеты; отдается, когда все готово.
+    ui: Option<AppUi>,
     render_cache: HashMap<String, Arc<DynamicImage>>,
-    assets_to_generate: Vec<AssetRequest>,
+    cache: AssetCache,
+    /// Модель, которой генерируются ассеты (часть ключа кэша).
+    model_id: String,
+    /// Логические ключи всех ассетов, которые нужны UI.
+    live_keys: Vec<String>,
+    collect_garbage: bool,
+    pool: GenerationPool,
+    /// Производные ассеты, которые ждут готовности исходного.
+    waiting: Vec<AssetSpec>,
+    /// Прогресс ассетов, которые генерируются прямо сейчас.
+    in_flight: BTreeMap<String, GenProgress>,
+    /// Последний частичный результат, который прислал генератор.
+    preview: Option<Arc<DynamicImage>>,
+    total_to_generate: usize,
+    failed: usize,
+    /// Подсказка, как исправить последнюю ошибку генерации.
+    last_error: Option<String>,
     current_status: String,
     is_done: bool,
 }
 
 impl LoadingState {
-    pub fn new(font: Arc<Font<'static>>, ai_renderer: Arc<AiRenderer>) -> Self {
-        // 1. Создать директорию кэша, если ее нет
-        fs::create_dir_all(CACHE_DIR).unwrap();
-
-        // 2. Определить все необходимые ассеты
-        let mut required_assets = Vec::new();
-        let mut temp_buttons = vec![
-            // ИСПРАВЛЕНИЕ: Конвертируем &str в String
-            Button::new(0, 50, 50, 200, 60, "Submit".to_string(), Arc::clone(&font)),
-            Button::new(1, 270, 50, 150, 60, "Clear".to_string(), Arc::clone(&font)),
-        ];
-
-        for button in &mut temp_buttons {
-            for (state_key, prompt) in button.get_render_prompts() {
-                required_assets.push(AssetRequest {
-                    key: state_key,
-                    prompt,
-                    width: button.width,
-                    height: button.height,
-                });
+    pub fn new(font: Arc<Font<'static>>, backend: Arc<dyn ImageBackend>, ui: AppUi, options: LoadingOptions) -> Self {
+        // 1. Открыть кэш (директория создается, если ее нет)
+        let mut cache = AssetCache::open(&options.cache_dir).unwrap();
+        let model_id = backend.model_id();
+
+        // 2. Определить все необходимые ассеты: их сообщают сами виджеты
+        let mut required_assets = ui.asset_specs();
+        for asset in required_assets.iter_mut() {
+            asset.fit = options.fit;
+            asset.filter = options.filter;
+        }
+        let live_keys: Vec<String> = required_assets.iter().map(|asset| asset.key.clone()).collect();
+
+        // 3. Назначить сиды и записать их, чтобы скин можно было повторить
+        if let Err(e) = SeedLock::open(&options.seed_lock).resolve(&mut required_assets, &options.seeds) {
+            println!("Failed to write seed lock '{}': {}", options.seed_lock, e);
+        }
+
+        // 4. Связать производные состояния с исходными: ключ кэша производного
+        //    зависит от хэша исходного ассета и силы img2img.
+        let derive = options.derive_states && backend.supports_img2img();
+        let mut content_hashes: HashMap<String, String> = HashMap::new();
+        for asset in required_assets.iter_mut() {
+            let source_hash = asset.derive_from.as_ref().and_then(|source| content_hashes.get(source)).cloned();
+            match source_hash {
+                Some(source_hash) if derive => {
+                    asset.init = Some(InitImage {
+                        source_hash,
+                        denoising_strength: options.denoising_strength,
+                        image: None,
+                    });
+                }
+                _ => asset.derive_from = None,
             }
+            content_hashes.insert(asset.key.clone(), AssetCache::content_hash(asset, &model_id));
         }
 
-        // 3. Проверить кэш на диске
+        // 5. Проверить кэш на диске: картинка подходит, только если совпали все параметры генерации
         let mut render_cache = HashMap::new();
-        let mut assets_to_generate = Vec::new();
+        let mut assets_to_generate: Vec<AssetSpec> = Vec::new();
 
         for asset in required_assets {
-            let path = Path::new(CACHE_DIR).join(format!("{}.png", asset.key));
-            if path.exists() {
-                // Загружаем из кэша
-                let image_bytes = fs::read(&path).unwrap();
-                if let Ok(image) = image::load_from_memory_with_format(&image_bytes, ImageFormat::Png) {
-                    render_cache.insert(asset.key, Arc::new(image));
-                }
+            if let Some(image) = cache.load(&asset, &model_id) {
+                render_cache.insert(asset.key, Arc::new(image));
             } else {
                 // Добавляем в очередь на генерацию
                 assets_to_generate.push(asset);
             }
         }
-        
+
         let initial_status = if assets_to_generate.is_empty() {
             "All assets loaded from cache. Starting...".to_string()
         } else {
             format!("Need to generate {} assets.", assets_to_generate.len())
         };
-        // Инвертируем очередь, чтобы pop() доставал элементы в правильном порядке
-        assets_to_generate.reverse();
+
+        // 6. Отдать недостающие ассеты пулу генерации. Производные ждут исходный,
+        //    если его картинки еще нет.
+        let total_to_generate = assets_to_generate.len();
+        let concurrency = options.concurrency.min(backend.max_concurrency());
+        let mut pool = GenerationPool::new(Arc::clone(&backend), concurrency);
+        let mut waiting = Vec::new();
+        for mut asset in assets_to_generate {
+            let source_image = asset.derive_from.as_ref().and_then(|source| render_cache.get(source));
+            match (&mut asset.init, source_image) {
+                (Some(init), Some(image)) => {
+                    init.image = Some(Arc::clone(image));
+                    pool.submit(asset);
+                }
+                (Some(_), None) => waiting.push(asset),
+                (None, _) => pool.submit(asset),
+            }
+        }
 
         Self {
             font,
-            ai_renderer,
+            backend,
+            ui: Some(ui),
             render_cache,
-            assets_to_generate,
+            cache,
+            model_id,
+            live_keys,
+            collect_garbage: options.collect_garbage,
+            pool,
+            waiting,
+            in_flight: BTreeMap::new(),
+            preview: None,
+            total_to_generate,
+            failed: 0,
+            last_error: None,
             current_status: initial_status,
             is_done: false,
         }
     }
 
-    /// Обновляет состояние загрузки. Если генерация закончена, возвращает готовый UI.
+    /// Опрашивает пул генерации. Не блокирует: вызывается каждый кадр.
+    /// Когда все ассеты готовы, возвращает готовый UI.
     pub fn update(&mut self) -> Option<AppUi> {
         if self.is_done {
             return None; // Уже отдали UI, больше ничего не делаем
         }
-        
-        if let Some(asset) = self.assets_to_generate.pop() {
-            self.current_status = format!("Generating: '{}'...", asset.key);
-            println!("{}", self.current_status); // Лог в консоль
-
-            // Это блокирующий вызов, он "заморозит" экран загрузки на время генерации.
-            if let Ok(image) = self.ai_renderer.generate_image(&asset.prompt, asset.width, asset.height) {
-                // Сохраняем в кэш на диске
-                let path = Path::new(CACHE_DIR).join(format!("{}.png", asset.key));
-                image.save(&path).unwrap();
-                // Сохраняем в кэш в памяти
-                self.render_cache.insert(asset.key, Arc::new(image));
-            } else {
-                self.current_status = format!("Error generating: '{}'!", asset.key);
-                println!("{}", self.current_status);
+
+        self.process_events();
+
+        if self.remaining() > 0 && self.is_cancelled() {
+            self.current_status = "Cancelling generation...".to_string();
+            return None;
+        }
+        if self.remaining() > 0 {
+            let done = self.total_to_generate - self.remaining();
+            self.current_status = format!("Generating assets: {}/{}", done, self.total_to_generate);
+            if self.failed > 0 {
+                self.current_status.push_str(&format!(" ({} failed)", self.failed));
+            }
+            return None;
+        }
+
+        // Генерация закончена!
+        self.current_status = "Generation complete! Finalizing...".to_string();
+        self.finish();
+        self.take_ui()
+    }
+
+    /// Отдает UI сразу, не дожидаясь генерации, с тем, что уже нашлось в кэше.
+    /// Остальные картинки досылаются в него через `stream_into`.
+    pub fn take_ui(&mut self) -> Option<AppUi> {
+        let mut ui = self.ui.take()?;
+        ui.set_render_cache(std::mem::take(&mut self.render_cache)); // Передаем владение кэшем
+        Some(ui)
+    }
+
+    /// Передает в уже работающий UI картинки, готовые с прошлого кадра.
+    /// Возвращает `false`, когда генерировать больше нечего.
+    pub fn stream_into(&mut self, ui: &mut AppUi) -> bool {
+        if self.is_done {
+            return false;
+        }
+        self.process_events();
+        // После `take_ui` в кэше оказываются только новые картинки
+        for (key, image) in self.render_cache.drain() {
+            ui.insert_asset(key, image);
+        }
+        if self.remaining() > 0 {
+            return true;
+        }
+        self.finish();
+        false
+    }
+
+    /// Разбирает события пула: сохраняет готовые картинки и запускает ждавшие их ассеты.
+    fn process_events(&mut self) {
+        for event in self.pool.poll() {
+            match event {
+                GenerationEvent::Progress { key, progress } => {
+                    if progress.preview.is_some() {
+                        self.preview = progress.preview.clone();
+                    }
+                    self.in_flight.insert(key, progress);
+                }
+                GenerationEvent::Finished { spec, result: Ok(generated), elapsed } => {
+                    self.in_flight.remove(&spec.key);
+                    // Сохраняем в кэш на диске вместе с происхождением картинки
+                    if self.backend.is_cacheable() {
+                        let metadata = AssetMetadata::new(&spec, self.backend.name(), &self.model_id, &generated.info, elapsed);
+                        if let Err(e) = self.cache.store(&spec, &self.model_id, &generated.image, &metadata) {
+                            println!("Failed to write '{}' to cache: {}", spec.key, e);
+                        }
+                    }
+                    println!("Generated: '{}'", spec.key); // Лог в консоль
+                    // Сохраняем в кэш в памяти
+                    let image = Arc::new(generated.image);
+                    self.render_cache.insert(spec.key.clone(), Arc::clone(&image));
+                    self.release_dependents(&spec.key, Some(image));
+                }
+                GenerationEvent::Finished { spec, result: Err(e), .. } => {
+                    self.in_flight.remove(&spec.key);
+                    self.failed += 1;
+                    println!("Error generating: '{}'! ({}: {})", spec.key, self.backend.name(), e);
+                    self.last_error = Some(e.hint().to_string());
+                    self.release_dependents(&spec.key, None);
+                }
+                GenerationEvent::Cancelled { spec } => {
+                    self.in_flight.remove(&spec.key);
+                    println!("Cancelled: '{}'", spec.key);
+                    // Ждавшие его ассеты рисуются с нуля; если отменен весь пул, он снимет и их
+                    self.release_dependents(&spec.key, None);
+                }
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        self.is_done = true;
+        self.report_stale_cache();
+    }
+
+    /// Сколько ассетов еще не готово: в пуле и в ожидании исходного.
+    fn remaining(&self) -> usize {
+        self.pool.pending() + self.waiting.len()
+    }
+
+    /// Отдает пулу ассеты, ждавшие исходный `source_key`. Если исходный не получился,
+    /// они генерируются с нуля через txt2img.
+    fn release_dependents(&mut self, source_key: &str, source_image: Option<Arc<DynamicImage>>) {
+        let (ready, still_waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.waiting)
+            .into_iter()
+            .partition(|asset| asset.derive_from.as_deref() == Some(source_key));
+        self.waiting = still_waiting;
+
+        for mut asset in ready {
+            match &source_image {
+                Some(image) => {
+                    if let Some(init) = &mut asset.init {
+                        init.image = Some(Arc::clone(image));
+                    }
+                }
+                None => {
+                    asset.init = None;
+                    asset.derive_from = None;
+                }
+            }
+            self.pool.submit(asset);
+        }
+    }
+
+    /// Сообщает об устаревших файлах кэша и удаляет их, если это разрешено.
+    fn report_stale_cache(&mut self) {
+        if self.collect_garbage {
+            match self.cache.collect_garbage(&self.live_keys) {
+                Ok(0) => {}
+                Ok(removed) => println!("Cache: removed {} stale files.", removed),
+                Err(e) => println!("Cache: garbage collection failed: {}", e),
             }
         } else {
-            // Генерация закончена!
-            self.current_status = "Generation complete! Finalizing...".to_string();
-            self.is_done = true;
-            
-            // Создаем и возвращаем полностью готовый AppUi
-            return Some(AppUi::new(
-                Arc::clone(&self.font),
-                Arc::clone(&self.ai_renderer),
-                std::mem::take(&mut self.render_cache), // Передаем владение кэшем
-            ));
+            let stale = self.cache.stale_files(&self.live_keys).len();
+            if stale > 0 {
+                println!("Cache: {} stale files, run with --gc-cache to remove them.", stale);
+            }
         }
+    }
+
+    /// Отменяет оставшиеся генерации. Недостающие фоны заменятся запасными.
+    pub fn cancel(&mut self) {
+        println!("Generation cancelled, starting with fallback skins.");
+        self.pool.cancel();
+        self.waiting.clear();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.pool.is_cancelled()
+    }
+
+    /// Ждет, пока сгенерируются все ассеты, и отдает готовый UI. Блокирует поток:
+    /// нужен там, где нет цикла событий, например для снимков экрана.
+    pub fn wait(&mut self) -> Option<AppUi> {
+        loop {
+            if let Some(ui) = self.update() {
+                return Some(ui);
+            }
+            if self.is_done {
+                return None;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
 
-        None
+    /// Рисует экран загрузки без окна в новый RGBA-буфер.
+    pub fn render(&self, bg_color: [u8; 4], clock: &dyn Clock, width: u32, height: u32) -> RgbaImage {
+        let mut image = RgbaImage::new(width, height);
+        let mut canvas = Canvas::new(&mut image, width, height);
+        canvas.clear(bg_color);
+        self.draw(&mut canvas, clock);
+        image
     }
 
-    /// Рисует нативный UI загрузки.
-    pub fn draw(&self, frame: &mut [u8], screen_width: u32) {
+    /// Рисует нативный UI загрузки: общий статус, прогресс каждого ассета и превью.
+    pub fn draw(&self, canvas: &mut Canvas, clock: &dyn Clock) {
+        let screen_width = canvas.width();
         let text_color = [200, 200, 200, 255];
+        let dim_color = [140, 140, 150, 255];
+        let track_color = [50, 50, 60, 255];
+        let accent_color = [40, 170, 255, 255];
+
+        // 1. Общий статус по центру
         let scale = Scale { x: 30.0, y: 30.0 };
-        let text = &self.current_status;
-        
-        let v_metrics = self.font.v_metrics(scale);
-        let glyphs_height = (v_metrics.ascent - v_metrics.descent).round() as u32;
-
-        let glyphs: Vec<_> = self.font.layout(text, scale, point(0.0, 0.0)).collect();
-        let width = glyphs.iter().map(|g| g.unpositioned().h_metrics().advance_width).sum::<f32>().round() as u32;
-        
-        let text_x = (screen_width.saturating_sub(width)) / 2;
-        let text_y = (600 - glyphs_height) / 2;
-        
-        let final_glyphs: Vec<_> = self.font.layout(text, scale, point(text_x as f32, text_y as f32 + v_metrics.ascent)).collect();
-
-        for glyph in final_glyphs {
-            if let Some(bounding_box) = glyph.pixel_bounding_box() {
-                glyph.draw(|gx, gy, v| {
-                    if v > 0.1 {
-                        let px = bounding_box.min.x + gx as i32;
-                        let py = bounding_box.min.y + gy as i32;
-                        if px >= 0 && py >= 0 {
-                            let index = ((py as u32 * screen_width) + px as u32) as usize * 4;
-                            if index + 3 < frame.len() {
-                                frame[index..index + 4].copy_from_slice(&text_color);
-                            }
-                        }
-                    }
-                });
+        let status_width = self.text_width(&self.current_status, scale);
+        let status_x = (screen_width.saturating_sub(status_width) / 2) as i32;
+        self.draw_line(canvas, &self.current_status, status_x, 120, scale, text_color);
+
+        // Что делать с последней ошибкой
+        if let Some(hint) = &self.last_error {
+            let hint_scale = Scale { x: 18.0, y: 18.0 };
+            let hint_x = (screen_width.saturating_sub(self.text_width(hint, hint_scale)) / 2) as i32;
+            self.draw_line(canvas, hint, hint_x, 80, hint_scale, [255, 120, 100, 255]);
+        }
+
+        if self.remaining() == 0 {
+            return;
+        }
+
+        // 2. Бегущий индикатор под текстом: показывает, что окно живо, пока идет генерация.
+        let track_width = 300;
+        let track_x = (screen_width.saturating_sub(track_width) / 2) as i32;
+        canvas.fill(Rect::new(track_x, 165, track_width, 4), track_color);
+
+        let segment = 60;
+        let phase = (clock.now().as_secs_f32() * 0.8).fract();
+        let offset = ((track_width + segment) as f32 * phase) as i32 - segment as i32;
+        let start = offset.max(0);
+        let end = (offset + segment as i32).min(track_width as i32);
+        if end > start {
+            canvas.fill(Rect::new(track_x + start, 165, (end - start) as u32, 4), accent_color);
+        }
+
+        // 3. Прогресс каждого ассета, который генерируется прямо сейчас
+        let list_x = 150;
+        let bar_width = screen_width.saturating_sub(2 * list_x as u32);
+        let label_scale = Scale { x: 18.0, y: 18.0 };
+        let mut row_y = 200;
+        for (key, progress) in &self.in_flight {
+            let mut label = format!("{}  {:>3.0}%", key, progress.fraction * 100.0);
+            if let Some(eta) = progress.eta {
+                label.push_str(&format!("  ETA {:.0}s", eta.as_secs_f32().ceil()));
+            }
+            self.draw_line(canvas, &label, list_x, row_y, label_scale, dim_color);
+
+            let bar_y = row_y + 24;
+            canvas.fill(Rect::new(list_x, bar_y, bar_width, 6), track_color);
+            let filled = (bar_width as f32 * progress.fraction) as u32;
+            if filled > 0 {
+                canvas.fill(Rect::new(list_x, bar_y, filled, 6), accent_color);
             }
+            row_y += 44;
+        }
+
+        // 4. Частично "проявленная" картинка
+        if let Some(preview) = &self.preview {
+            let preview_y = row_y + 10;
+            self.draw_line(canvas, "Preview", list_x, preview_y, label_scale, dim_color);
+            canvas.blit(preview, list_x, preview_y + 26, 1.0);
         }
     }
-}
\ No newline at end of file
+
+    fn text_width(&self, text: &str, scale: Scale) -> u32 {
+        self.font
+            .layout(text, scale, point(0.0, 0.0))
+            .map(|g| g.unpositioned().h_metrics().advance_width)
+            .sum::<f32>()
+            .round() as u32
+    }
+
+    /// Рисует строку текста, у которой `(x, y)` — левый верхний угол.
+    fn draw_line(&self, canvas: &mut Canvas, text: &str, x: i32, y: i32, scale: Scale, color: [u8; 4]) {
+        let v_metrics = self.font.v_metrics(scale);
+        canvas.text(&self.font, text, scale, (x as f32, y as f32 + v_metrics.ascent), color);
+    }
+}
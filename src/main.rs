@@ -1,7 +1,10 @@
 // src/main.rs
 
 use pixels::{Pixels, SurfaceTexture};
+use rusttype::Font;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 // ИСПРАВЛЕНИЕ (warning): Убран неиспользуемый импорт `PhysicalPosition`.
 use winit::dpi::LogicalSize;
 use winit::event::{Event, WindowEvent};
@@ -9,42 +12,241 @@ use winit::event_loop::{ControlFlow, EventLoop};
 use winit::keyboard::{Key, NamedKey};
 use winit::window::Window;
 
-// Подключаем наши модули
-mod ai_renderer;
-mod ui;
-mod loading; 
-
-use ai_renderer::AiRenderer;
-use loading::LoadingState;
-use ui::AppUi;
+use shadowin::ai_renderer::{
+    AiRenderer, DEFAULT_BASE_URL, DEFAULT_CONNECT_TIMEOUT, DEFAULT_NATIVE_RESOLUTION, DEFAULT_REQUEST_TIMEOUT,
+};
+use shadowin::app::{main_screen_actions, run_frame};
+use shadowin::backend::procedural::ProceduralBackend;
+use shadowin::backend::ImageBackend;
+use shadowin::cache::AssetCache;
+use shadowin::clock::{ManualClock, SystemClock};
+use shadowin::headless::{render_screen, ScreenState};
+use shadowin::loading::{LoadingOptions, LoadingState};
+use shadowin::ui::widget::{InputTranslator, UiEvent};
+use shadowin::ui::canvas::Canvas;
+use shadowin::ui::screen::{ScreenDef, ScreenError, ScreenWatcher, DEFAULT_SCREEN_FILE};
+use shadowin::ui::AppUi;
+use shadowin::AppState;
 
 const WIDTH: u32 = 800;
 const HEIGHT: u32 = 600;
 
-/// Глобальное состояние, которое хранит данные, нужные для логики приложения.
-pub struct AppState {
-    pub mouse_pos: (i32, i32),
-    pub mouse_pressed: bool,
-    pub message: String,
-    pub bg_color: [u8; 4],
-    pub click_count: u32,
-    pub text_input_content: String,
-}
-
 /// Перечисление, управляющее тем, какой "экран" сейчас активен.
 enum AppMode {
-    Loading(LoadingState),
+    Loading(Box<LoadingState>),
     Running(AppUi),
 }
 
+/// Параметры командной строки.
+struct CliArgs {
+    /// `--offline`: не обращаться к Stable Diffusion.
+    offline: bool,
+    /// `--sd-url URL`: адрес `stable-diffusion-webui`.
+    sd_url: String,
+    /// `--timeout SECS`: сколько ждать ответа на одну генерацию.
+    request_timeout: Duration,
+    /// `--native-resolution N`: родное разрешение модели (512 для SD 1.5, 1024 для SDXL).
+    native_resolution: u32,
+    /// `--jobs N`, `--gc-cache`, `--seed N`, `--independent-seeds`, `--no-derive`, `--denoise X`,
+    /// `--fit MODE`, `--filter NAME`: настройки загрузки ассетов.
+    loading: LoadingOptions,
+    /// `--asset-info KEY`: напечатать происхождение ассета и выйти.
+    asset_info: Option<String>,
+    /// `--progressive`: сразу показать UI и догружать фоны в процессе работы.
+    progressive: bool,
+    /// `--ui PATH`: файл описания экрана; перечитывается при изменении.
+    screen_file: String,
+    /// `render ...`: снять экран в PNG без окна и выйти.
+    render: Option<RenderArgs>,
+}
+
+/// Параметры подкоманды `render`.
+struct RenderArgs {
+    /// `--out PATH`: куда записать PNG.
+    out: String,
+    /// `--size WxH`: размер снимка.
+    size: (u32, u32),
+    /// `--pointer X,Y`, `--pressed`, `--text TEXT`, `--message TEXT`: состояние виджетов.
+    state: ScreenState,
+    /// `--loading`: снять экран загрузки, а не готовый UI.
+    loading: bool,
+}
+
+impl Default for RenderArgs {
+    fn default() -> Self {
+        Self {
+            out: "screenshot.png".to_string(),
+            size: (WIDTH, HEIGHT),
+            state: ScreenState::default(),
+            loading: false,
+        }
+    }
+}
+
+impl CliArgs {
+    fn parse() -> Result<Self, String> {
+        let mut cli = CliArgs {
+            offline: false,
+            sd_url: DEFAULT_BASE_URL.to_string(),
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            native_resolution: DEFAULT_NATIVE_RESOLUTION,
+            loading: LoadingOptions::default(),
+            asset_info: None,
+            progressive: false,
+            screen_file: DEFAULT_SCREEN_FILE.to_string(),
+            render: None,
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--offline" => cli.offline = true,
+                "--progressive" => cli.progressive = true,
+                "--ui" => cli.screen_file = args.next().ok_or("--ui expects a path to a screen description")?,
+                "--sd-url" => cli.sd_url = args.next().ok_or("--sd-url expects a URL")?,
+                "--timeout" => {
+                    let value = args.next().ok_or("--timeout expects a number of seconds")?;
+                    let secs: f32 = value.parse().map_err(|_| format!("invalid --timeout value: '{}'", value))?;
+                    cli.request_timeout = Duration::from_secs_f32(secs);
+                }
+                "--jobs" => {
+                    let value = args.next().ok_or("--jobs expects a number")?;
+                    cli.loading.concurrency = value.parse().map_err(|_| format!("invalid --jobs value: '{}'", value))?;
+                }
+                "--gc-cache" => cli.loading.collect_garbage = true,
+                "--seed" => {
+                    let value = args.next().ok_or("--seed expects a number")?;
+                    cli.loading.seeds.theme_seed = Some(value.parse().map_err(|_| format!("invalid --seed value: '{}'", value))?);
+                }
+                "--independent-seeds" => cli.loading.seeds.share_across_states = false,
+                "--no-derive" => cli.loading.derive_states = false,
+                "--fit" => cli.loading.fit = args.next().ok_or("--fit expects cover, contain or stretch")?.parse()?,
+                "--filter" => cli.loading.filter = args.next().ok_or("--filter expects nearest, linear, cubic or lanczos")?.parse()?,
+                "--native-resolution" => {
+                    let value = args.next().ok_or("--native-resolution expects a number")?;
+                    cli.native_resolution = value.parse().map_err(|_| format!("invalid --native-resolution value: '{}'", value))?;
+                }
+                "--denoise" => {
+                    let value = args.next().ok_or("--denoise expects a number between 0 and 1")?;
+                    cli.loading.denoising_strength = value.parse().map_err(|_| format!("invalid --denoise value: '{}'", value))?;
+                }
+                "--asset-info" => cli.asset_info = Some(args.next().ok_or("--asset-info expects an asset key")?),
+                "render" => cli.render = Some(RenderArgs::default()),
+                "--out" => cli.render_args("--out")?.out = args.next().ok_or("--out expects a path")?,
+                "--size" => {
+                    let value = args.next().ok_or("--size expects WIDTHxHEIGHT")?;
+                    cli.render_args("--size")?.size = parse_pair(&value, 'x').ok_or_else(|| format!("invalid --size value: '{}'", value))?;
+                }
+                "--pointer" => {
+                    let value = args.next().ok_or("--pointer expects X,Y")?;
+                    cli.render_args("--pointer")?.state.pointer = Some(parse_pair(&value, ',').ok_or_else(|| format!("invalid --pointer value: '{}'", value))?);
+                }
+                "--pressed" => cli.render_args("--pressed")?.state.pressed = true,
+                "--text" => cli.render_args("--text")?.state.text = Some(args.next().ok_or("--text expects a string")?),
+                "--message" => cli.render_args("--message")?.state.message = Some(args.next().ok_or("--message expects a string")?),
+                "--loading" => cli.render_args("--loading")?.loading = true,
+                other => return Err(format!("unknown argument: '{}'", other)),
+            }
+        }
+        Ok(cli)
+    }
+
+    /// Параметры `render`; флаги снимка допустимы только после этой подкоманды.
+    fn render_args(&mut self, flag: &str) -> Result<&mut RenderArgs, String> {
+        self.render.as_mut().ok_or_else(|| format!("{} is only valid after 'render'", flag))
+    }
+}
+
+/// Разбирает пару чисел вида `800x600` или `120,40`.
+fn parse_pair<T: std::str::FromStr>(value: &str, separator: char) -> Option<(T, T)> {
+    let (a, b) = value.split_once(separator)?;
+    Some((a.trim().parse().ok()?, b.trim().parse().ok()?))
+}
+
+/// Выбирает генератор фонов: Stable Diffusion, если он доступен, иначе процедурный.
+fn choose_backend(cli: &CliArgs) -> Arc<dyn ImageBackend> {
+    if cli.offline {
+        println!("Offline mode: using the procedural skin generator.");
+        return Arc::new(ProceduralBackend::new());
+    }
+
+    let ai_renderer = AiRenderer::with_base_url(&cli.sd_url)
+        .with_timeouts(DEFAULT_CONNECT_TIMEOUT, cli.request_timeout)
+        .with_native_resolution(cli.native_resolution);
+    if ai_renderer.is_available() {
+        Arc::new(ai_renderer)
+    } else {
+        println!("Stable Diffusion at {} is unreachable, falling back to the procedural skin generator.", cli.sd_url);
+        Arc::new(ProceduralBackend::new())
+    }
+}
+
+/// Строит UI из файла описания экрана, а если файла нет — встроенный экран.
+fn build_ui(screen_file: &str, font: &Arc<Font<'static>>, backend: &Arc<dyn ImageBackend>) -> Result<AppUi, ScreenError> {
+    if !Path::new(screen_file).exists() {
+        println!("Screen description '{}' not found, using the built-in screen.", screen_file);
+        return Ok(AppUi::new(Arc::clone(font), Arc::clone(backend)));
+    }
+    let screen = ScreenDef::load(screen_file)?;
+    AppUi::from_screen(&screen, Arc::clone(font), Arc::clone(backend))
+}
+
+fn load_font() -> Result<Arc<Font<'static>>, &'static str> {
+    let font_data = include_bytes!("../assets/font.ttf");
+    Ok(Arc::new(Font::try_from_bytes(font_data).ok_or("Failed to load font")?))
+}
+
+/// Снимает экран без окна: дожидается ассетов, приводит виджеты в заданное состояние
+/// и записывает PNG.
+fn render_screenshot(cli: &CliArgs, render: &RenderArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let font = load_font()?;
+    let backend = choose_backend(cli);
+    let ui = build_ui(&cli.screen_file, &font, &backend)?;
+    let mut loading_state = LoadingState::new(font, backend, ui, cli.loading.clone());
+    let mut app_state = AppState::default();
+    let (width, height) = render.size;
+    // Снимок не зависит от того, сколько длилась загрузка: время стоит на нуле
+    let clock = ManualClock::new();
+
+    let image = if render.loading {
+        loading_state.render(app_state.bg_color, &clock, width, height)
+    } else {
+        let mut ui = loading_state.wait().ok_or("UI is not available")?;
+        app_state.message = "AI Renderer is ready.".to_string();
+        render_screen(&mut ui, &mut app_state, &render.state, &clock, width, height)
+    };
+    image.save(&render.out)?;
+    println!("Saved {}x{} screenshot to '{}'.", width, height, render.out);
+    Ok(())
+}
+
+/// Печатает метаданные ассета из кэша: каким промптом и сидом он получен.
+fn print_asset_info(cache_dir: &str, key: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let cache = AssetCache::open(cache_dir)?;
+    match cache.metadata(key) {
+        Some(metadata) => println!("{}", serde_json::to_string_pretty(&metadata)?),
+        None => println!("No metadata for '{}' in '{}'.", key, cache_dir),
+    }
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // --- 1. Инициализация ---
+    let cli = CliArgs::parse()?;
+
+    if let Some(key) = &cli.asset_info {
+        return print_asset_info(&cli.loading.cache_dir, key);
+    }
+    if let Some(render) = &cli.render {
+        return render_screenshot(&cli, render);
+    }
+
     let event_loop = EventLoop::new()?;
     event_loop.set_control_flow(ControlFlow::Poll);
 
     // Примечание: `winit` рекомендует создавать окно внутри замыкания `run`,
     // но для совместимости с `pixels` мы создаем его здесь.
     // Это вызывает предупреждение о `deprecated`, но на работу не влияет.
+    #[allow(deprecated)]
     let window = Arc::new({
         let size = LogicalSize::new(WIDTH as f64, HEIGHT as f64);
         let attributes = Window::default_attributes()
@@ -59,6 +261,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // что решает конфликт владения.
     let window_clone = Arc::clone(&window);
 
+    // Без этого события IME (и на части систем — ввод не-латиницы) до окна не доходят.
+    window.set_ime_allowed(true);
+
     let mut pixels = {
         let window_size = window.inner_size();
         // `pixels` заимствует `window` здесь. Это заимствование будет жить
@@ -67,33 +272,50 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         pollster::block_on(Pixels::new_async(WIDTH, HEIGHT, surface_texture))?
     };
 
-    let font_data = include_bytes!("../assets/font.ttf");
-    let font = Arc::new(rusttype::Font::try_from_bytes(font_data).ok_or("Failed to load font")?);
+    let font = load_font()?;
     
-    let ai_renderer = Arc::new(AiRenderer::new());
-
-    let mut app_state = AppState {
-        mouse_pos: (-1, -1),
-        mouse_pressed: false,
-        message: "AI Renderer is initializing...".to_string(),
-        bg_color: [20, 20, 30, 255],
-        click_count: 0,
-        text_input_content: String::new(),
-    };
+    let backend = choose_backend(&cli);
 
-    let mut mode = AppMode::Loading(LoadingState::new(Arc::clone(&font), Arc::clone(&ai_renderer)));
+    let mut app_state = AppState::default();
+
+    let ui = build_ui(&cli.screen_file, &font, &backend)?;
+    let mut loading_state = Box::new(LoadingState::new(Arc::clone(&font), Arc::clone(&backend), ui, cli.loading.clone()));
+    let mut screen_watcher = ScreenWatcher::new(&cli.screen_file);
+    let mut actions = main_screen_actions();
+    let mut input = InputTranslator::new();
+    let clock = SystemClock::new();
+
+    // Размер буфера кадра; меняется вместе с окном, и UI раскладывается заново.
+    let mut frame_size = (WIDTH, HEIGHT);
+
+    // В прогрессивном режиме UI работает сразу, а генерация идет в фоне
+    let mut background_loading = None;
+    let mut mode = if cli.progressive {
+        let mut ui = loading_state.take_ui().ok_or("UI is not available")?;
+        ui.layout(frame_size.0, frame_size.1);
+        app_state.message = "Generating skins in the background...".to_string();
+        background_loading = Some(loading_state);
+        AppMode::Running(ui)
+    } else {
+        AppMode::Loading(loading_state)
+    };
 
     // --- 2. Главный Цикл ---
     // Примечание: `event_loop.run` также является `deprecated`.
     // Современный API `winit` использует `run_app`, но это требует
     // рефакторинга всей структуры приложения.
+    #[allow(deprecated)]
     event_loop.run(move |event, elwt| {
-        let mut mouse_clicked_this_frame = false;
+        let mut ui_event = None;
         if let Event::WindowEvent { event, .. } = &event {
             match event {
                 WindowEvent::CloseRequested => elwt.exit(),
                 WindowEvent::KeyboardInput { event: key_event, .. } if key_event.state.is_pressed() && key_event.logical_key == Key::Named(NamedKey::Escape) => {
-                    elwt.exit()
+                    match &mut mode {
+                        // На экране загрузки Escape отменяет генерацию, и UI запускается с запасными фонами
+                        AppMode::Loading(loading_state) if !loading_state.is_cancelled() => loading_state.cancel(),
+                        _ => elwt.exit(),
+                    }
                 }
                 WindowEvent::CursorMoved { position, .. } => {
                     if let Ok((x, y)) = pixels.window_pos_to_pixel((*position).into()) {
@@ -101,66 +323,83 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     } else {
                         app_state.mouse_pos = (-1, -1);
                     }
+                    ui_event = Some(UiEvent::MouseMoved { x: app_state.mouse_pos.0, y: app_state.mouse_pos.1 });
                 }
-                WindowEvent::MouseInput { state, button: winit::event::MouseButton::Left, .. } => {
-                    let is_pressed = *state == winit::event::ElementState::Pressed;
-                    if !is_pressed && app_state.mouse_pressed {
-                        mouse_clicked_this_frame = true;
+                WindowEvent::Resized(size) if size.width > 0 && size.height > 0 => {
+                    if pixels.resize_surface(size.width, size.height).is_err() || pixels.resize_buffer(size.width, size.height).is_err() {
+                        elwt.exit();
+                    }
+                    frame_size = (size.width, size.height);
+                    if let AppMode::Running(app_ui) = &mut mode {
+                        app_ui.layout(frame_size.0, frame_size.1);
                     }
-                    app_state.mouse_pressed = is_pressed;
                 }
-                WindowEvent::Resized(size) => {
-                    if let Err(_) = pixels.resize_surface(size.width, size.height) { elwt.exit(); }
+                other => {
+                    ui_event = input.translate(other);
+                    if let Some(ui_event) = &ui_event {
+                        app_state.track_pointer(ui_event);
+                    }
                 }
-                _ => {}
             }
         }
 
         // --- 3. Логика и Отрисовка в зависимости от режима ---
         match &mut mode {
             AppMode::Loading(loading_state) => {
-                if let Some(finished_ui) = loading_state.update() {
+                if let Some(mut finished_ui) = loading_state.update() {
+                    finished_ui.layout(frame_size.0, frame_size.1);
                     mode = AppMode::Running(finished_ui);
                     app_state.message = "AI Renderer is ready.".to_string();
                 }
             }
             AppMode::Running(app_ui) => {
-                if let Some(clicked_id) = app_ui.update(&app_state, mouse_clicked_this_frame, &event) {
-                    app_state.click_count += 1;
-                    match clicked_id {
-                        0 => { // Submit
-                            if app_state.text_input_content == "shadowin" {
-                                app_state.message = "Welcome, master.".to_string();
-                                app_state.bg_color = [40, 20, 20, 255];
-                            } else {
-                                app_state.message = format!("Submitted: {}", app_state.text_input_content);
-                            }
-                        },
-                        1 => { // Clear
-                            app_ui.text_input.text.clear();
-                            app_state.text_input_content.clear(); 
-                            app_state.message = "Cleared.".to_string();
-                            app_state.bg_color = [20, 20, 30, 255];
-                            app_state.click_count = 0;
-                        },
-                        _ => {}
+                // Файл описания изменился: строим экран заново, недостающие фоны догружаются в фоне
+                let reloaded = screen_watcher
+                    .poll()
+                    .map(|screen| screen.and_then(|screen| AppUi::from_screen(&screen, Arc::clone(&font), Arc::clone(&backend))));
+                match reloaded {
+                    Some(Ok(ui)) => {
+                        println!("Reloaded screen description '{}'.", screen_watcher.path().display());
+                        // Старую загрузку снимаем до запуска новой: ее interrupt прервал бы
+                        // на сервере первую задачу нового пула
+                        if let Some(mut previous) = background_loading.take() {
+                            previous.cancel();
+                        }
+                        let mut loading_state = Box::new(LoadingState::new(Arc::clone(&font), Arc::clone(&backend), ui, cli.loading.clone()));
+                        if let Some(mut ui) = loading_state.take_ui() {
+                            ui.layout(frame_size.0, frame_size.1);
+                            ui.write_bindings(&app_state);
+                            *app_ui = ui;
+                            background_loading = Some(loading_state);
+                        }
+                    }
+                    Some(Err(e)) => println!("{}", e),
+                    None => {}
+                }
+                if let Some(loading_state) = &mut background_loading {
+                    if !loading_state.stream_into(app_ui) {
+                        background_loading = None;
+                        // Не затираем сообщение, если пользователь уже что-то сделал
+                        if app_state.click_count == 0 {
+                            app_state.message = "AI Renderer is ready.".to_string();
+                        }
                     }
                 }
-                app_state.text_input_content = app_ui.text_input.text.clone();
+                run_frame(&mut app_state, app_ui, &mut actions, ui_event.as_ref(), &clock);
             }
         }
         
         // --- 4. Отрисовка ---
         if let Event::WindowEvent { event: WindowEvent::RedrawRequested, .. } = event {
-            let frame = pixels.frame_mut();
-            frame.chunks_exact_mut(4).for_each(|pixel| pixel.copy_from_slice(&app_state.bg_color));
+            let mut canvas = Canvas::new(pixels.frame_mut(), frame_size.0, frame_size.1);
+            canvas.clear(app_state.bg_color);
 
             match &mut mode {
-                AppMode::Loading(loading_state) => loading_state.draw(frame, WIDTH),
-                AppMode::Running(app_ui) => app_ui.draw(&app_state, frame, WIDTH),
+                AppMode::Loading(loading_state) => loading_state.draw(&mut canvas, &clock),
+                AppMode::Running(app_ui) => app_ui.draw(&app_state, &mut canvas, &clock),
             }
 
-            if let Err(_) = pixels.render() { elwt.exit(); }
+            if pixels.render().is_err() { elwt.exit(); }
         }
         
         // Используем клон, который был перемещен в замыкание.
@@ -0,0 +1,143 @@
+// src/pipeline.rs
+
+use crate::backend::{AssetSpec, CancelToken, GenError, GenProgress, GeneratedImage, ImageBackend};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Результат одной задачи пула.
+pub enum GenerationEvent {
+    /// Промежуточный прогресс генерации ассета с ключом `key`.
+    Progress { key: String, progress: GenProgress },
+    /// Генератор закончил работу (успешно или с ошибкой).
+    Finished {
+        spec: AssetSpec,
+        result: Result<GeneratedImage, GenError>,
+        /// Сколько заняла сама генерация.
+        elapsed: Duration,
+    },
+    /// Задача снята: пул был отменен до или во время генерации.
+    Cancelled { spec: AssetSpec },
+}
+
+/// Пул потоков, который генерирует ассеты в фоне.
+/// Задачи берутся из общей очереди, результаты приходят в канал, который опрашивается каждый кадр.
+pub struct GenerationPool {
+    backend: Arc<dyn ImageBackend>,
+    jobs: Option<Sender<AssetSpec>>,
+    results: Receiver<GenerationEvent>,
+    cancelled: CancelToken,
+    pending: usize,
+}
+
+impl GenerationPool {
+    /// Запускает `concurrency` рабочих потоков (минимум один).
+    pub fn new(backend: Arc<dyn ImageBackend>, concurrency: usize) -> Self {
+        let (job_tx, job_rx) = mpsc::channel::<AssetSpec>();
+        let (result_tx, result_rx) = mpsc::channel();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        // У каждого пула свой флаг: отмена прошлого пула не касается задач этого
+        let cancelled = CancelToken::new();
+
+        for worker_id in 0..concurrency.max(1) {
+            let job_rx = Arc::clone(&job_rx);
+            let result_tx = result_tx.clone();
+            let backend = Arc::clone(&backend);
+            let cancelled = cancelled.clone();
+
+            thread::Builder::new()
+                .name(format!("shadowin-gen-{}", worker_id))
+                .spawn(move || worker_loop(backend, job_rx, result_tx, cancelled))
+                .expect("failed to spawn generation worker");
+        }
+
+        Self {
+            backend,
+            jobs: Some(job_tx),
+            results: result_rx,
+            cancelled,
+            pending: 0,
+        }
+    }
+
+    /// Ставит ассет в очередь на генерацию.
+    pub fn submit(&mut self, spec: AssetSpec) {
+        if let Some(jobs) = &self.jobs {
+            if jobs.send(spec).is_ok() {
+                self.pending += 1;
+            }
+        }
+    }
+
+    /// Забирает все готовые результаты, не блокируя поток.
+    pub fn poll(&mut self) -> Vec<GenerationEvent> {
+        let events: Vec<_> = self.results.try_iter().collect();
+        let finished = events.iter().filter(|e| !matches!(e, GenerationEvent::Progress { .. })).count();
+        self.pending -= finished;
+        events
+    }
+
+    /// Сколько задач еще не вернули результат.
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.is_cancelled()
+    }
+
+    /// Отменяет все задачи: очередь сбрасывается, текущие генерации прерываются, если бэкенд это умеет.
+    pub fn cancel(&mut self) {
+        if !self.cancelled.is_cancelled() {
+            self.cancelled.cancel();
+            // Без своих задач не прерываем: на сервере может идти чужая генерация
+            if self.pending > 0 {
+                self.backend.interrupt();
+            }
+        }
+    }
+}
+
+impl Drop for GenerationPool {
+    fn drop(&mut self) {
+        // Не ждем потоки: закрытая очередь и флаг отмены сами завершат их.
+        if self.pending > 0 {
+            self.cancel();
+        }
+        self.jobs.take();
+    }
+}
+
+fn worker_loop(
+    backend: Arc<dyn ImageBackend>,
+    jobs: Arc<Mutex<Receiver<AssetSpec>>>,
+    results: Sender<GenerationEvent>,
+    cancelled: CancelToken,
+) {
+    loop {
+        // Блокировку держим только на время получения задачи.
+        let job = jobs.lock().unwrap().recv();
+        let Ok(spec) = job else { break };
+
+        let event = if cancelled.is_cancelled() {
+            GenerationEvent::Cancelled { spec }
+        } else {
+            let mut report = |progress: GenProgress| {
+                let _ = results.send(GenerationEvent::Progress { key: spec.key.clone(), progress });
+            };
+            let started = Instant::now();
+            let result = backend.generate_with_progress(&spec, &cancelled, &mut report);
+            // Прерванный результат недорисован, даже если прервал не этот пул: в кэш он попасть не должен
+            if cancelled.is_cancelled() || matches!(result, Err(GenError::Interrupted)) {
+                GenerationEvent::Cancelled { spec }
+            } else {
+                GenerationEvent::Finished { spec, result, elapsed: started.elapsed() }
+            }
+        };
+
+        if results.send(event).is_err() {
+            break; // Пул уже удален
+        }
+    }
+}
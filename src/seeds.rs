@@ -0,0 +1,183 @@
+// src/seeds.rs
+
+use crate::backend::{fnv1a, AssetSpec};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::hash::BuildHasher;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Файл с сидами скина по умолчанию. Его стоит хранить в репозитории рядом с кодом.
+pub const DEFAULT_SEED_LOCK: &str = "seeds.lock.json";
+
+/// Как выбирать сиды для ассетов, у которых виджет не задал свой.
+#[derive(Clone, Debug)]
+pub struct SeedPolicy {
+    /// Сид темы: все сиды скина выводятся из него детерминированно.
+    /// `None` — сиды случайные, но записываются в lock-файл.
+    pub theme_seed: Option<u64>,
+    /// Один сид на все состояния виджета, чтобы Idle/Hovered/Pressed были похожи.
+    pub share_across_states: bool,
+}
+
+impl Default for SeedPolicy {
+    fn default() -> Self {
+        Self { theme_seed: None, share_across_states: true }
+    }
+}
+
+/// Сиды, которыми был сгенерирован скин: логический ключ -> сид.
+#[derive(Serialize, Deserialize, Default)]
+struct SeedLockFile {
+    seeds: BTreeMap<String, i64>,
+}
+
+/// Lock-файл сидов. Скопировав его на другую машину, можно повторить скин в точности.
+pub struct SeedLock {
+    path: PathBuf,
+    file: SeedLockFile,
+}
+
+impl SeedLock {
+    /// Читает lock-файл; если его нет, начинает с пустого.
+    pub fn open(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let file = fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        Self { path, file }
+    }
+
+    pub fn get(&self, key: &str) -> Option<i64> {
+        self.file.seeds.get(key).copied()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let bytes = serde_json::to_vec_pretty(&self.file).map_err(io::Error::other)?;
+        fs::write(&self.path, bytes)
+    }
+
+    /// Назначает сид каждому ассету и записывает итог в lock-файл.
+    ///
+    /// Приоритет: сид, заданный виджетом; сид из темы; сид из lock-файла; новый случайный.
+    pub fn resolve(&mut self, specs: &mut [AssetSpec], policy: &SeedPolicy) -> io::Result<()> {
+        let random = RandomState::new();
+
+        // Сид из lock-файла становится сидом всей группы: так новое состояние
+        // виджета получит тот же сид, что и уже сгенерированные.
+        let mut group_seeds: HashMap<String, i64> = HashMap::new();
+        for spec in specs.iter().filter(|spec| spec.seed < 0) {
+            if let Some(seed) = self.get(&spec.key) {
+                group_seeds.entry(seed_group(spec, policy)).or_insert(seed);
+            }
+        }
+
+        for spec in specs.iter_mut() {
+            if spec.seed < 0 {
+                let group = seed_group(spec, policy);
+                spec.seed = match policy.theme_seed {
+                    Some(theme) => derive_seed(theme, &group),
+                    None => self.get(&spec.key).unwrap_or_else(|| {
+                        *group_seeds
+                            .entry(group.clone())
+                            .or_insert_with(|| (random.hash_one(&group) as u32) as i64)
+                    }),
+                };
+            }
+            self.file.seeds.insert(spec.key.clone(), spec.seed);
+        }
+
+        self.save()
+    }
+}
+
+/// Ассеты одной группы получают один и тот же сид.
+fn seed_group(spec: &AssetSpec, policy: &SeedPolicy) -> String {
+    if policy.share_across_states {
+        spec.widget.clone()
+    } else {
+        spec.key.clone()
+    }
+}
+
+/// Детерминированный сид для группы: одинаковый на всех машинах.
+/// Результат умещается в 32 бита, как того ожидает Stable Diffusion.
+pub fn derive_seed(theme_seed: u64, group: &str) -> i64 {
+    fnv1a(&format!("{}:{}", theme_seed, group)) as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Путь к временному lock-файлу; файл удаляется вместе с тестом.
+    struct TempLock(PathBuf);
+
+    impl TempLock {
+        fn new(name: &str, seeds: &[(&str, i64)]) -> Self {
+            let path = std::env::temp_dir().join(format!("shadowin-seeds-{}-{}.json", name, std::process::id()));
+            let file = SeedLockFile { seeds: seeds.iter().map(|&(key, seed)| (key.to_string(), seed)).collect() };
+            fs::write(&path, serde_json::to_vec(&file).unwrap()).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempLock {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn spec(widget: &str, state: &str) -> AssetSpec {
+        AssetSpec::new(format!("{}-{}", widget, state), "stone button".to_string(), 8, 4).with_widget(widget.to_string())
+    }
+
+    fn resolve(lock: &TempLock, mut specs: Vec<AssetSpec>, policy: &SeedPolicy) -> Vec<i64> {
+        SeedLock::open(&lock.0).resolve(&mut specs, policy).unwrap();
+        specs.iter().map(|spec| spec.seed).collect()
+    }
+
+    #[test]
+    fn widget_seed_beats_theme_beats_lock_beats_random() {
+        let lock = TempLock::new("priority", &[("0-Idle", 5), ("1-Idle", 6)]);
+        let themed = SeedPolicy { theme_seed: Some(99), share_across_states: false };
+        let specs = || vec![spec("0", "Idle").with_seed(7), spec("1", "Idle"), spec("2", "Idle")];
+
+        let seeds = resolve(&lock, specs(), &themed);
+        assert_eq!(seeds, vec![7, derive_seed(99, "1-Idle"), derive_seed(99, "2-Idle")]);
+
+        let lock = TempLock::new("priority-lock", &[("0-Idle", 5), ("1-Idle", 6)]);
+        let seeds = resolve(&lock, specs(), &SeedPolicy { theme_seed: None, share_across_states: false });
+        assert_eq!(&seeds[..2], &[7, 6]);
+        assert!((0..=u32::MAX as i64).contains(&seeds[2]));
+        assert_eq!(SeedLock::open(&lock.0).get("2-Idle"), Some(seeds[2]));
+    }
+
+    #[test]
+    fn states_of_one_widget_share_a_seed() {
+        let lock = TempLock::new("share", &[]);
+        let specs = || vec![spec("0", "Idle"), spec("0", "Hovered"), spec("1", "Idle")];
+
+        let shared = resolve(&lock, specs(), &SeedPolicy::default());
+        assert_eq!(shared[0], shared[1]);
+
+        let themed = SeedPolicy { theme_seed: Some(99), share_across_states: true };
+        assert_eq!(resolve(&lock, specs(), &themed), vec![derive_seed(99, "0"), derive_seed(99, "0"), derive_seed(99, "1")]);
+
+        let separate = SeedPolicy { theme_seed: Some(99), share_across_states: false };
+        let seeds = resolve(&lock, specs(), &separate);
+        assert_ne!(seeds[0], seeds[1]);
+    }
+
+    #[test]
+    fn new_state_inherits_its_group_seed_from_the_lock() {
+        let lock = TempLock::new("inherit", &[("0-Idle", 123)]);
+        let seeds = resolve(&lock, vec![spec("0", "Pressed"), spec("0", "Idle")], &SeedPolicy::default());
+
+        assert_eq!(seeds, vec![123, 123]);
+        assert_eq!(SeedLock::open(&lock.0).get("0-Pressed"), Some(123));
+    }
+}
@@ -0,0 +1,42 @@
+// src/ui/action.rs
+
+use crate::ui::AppUi;
+use crate::AppState;
+use std::collections::HashMap;
+
+/// Обработчик именованного действия: меняет состояние приложения и, если нужно, сам UI.
+pub type ActionHandler = Box<dyn FnMut(&mut AppState, &mut AppUi)>;
+
+/// Реестр действий. Кнопки знают только имя действия (`"submit"`, `"clear"`),
+/// а что оно делает, регистрирует код приложения.
+#[derive(Default)]
+pub struct Actions {
+    handlers: HashMap<String, ActionHandler>,
+}
+
+impl Actions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Регистрирует обработчик действия `name`, заменяя прежний.
+    pub fn on(mut self, name: &str, handler: impl FnMut(&mut AppState, &mut AppUi) + 'static) -> Self {
+        self.register(name, handler);
+        self
+    }
+
+    pub fn register(&mut self, name: &str, handler: impl FnMut(&mut AppState, &mut AppUi) + 'static) {
+        self.handlers.insert(name.to_string(), Box::new(handler));
+    }
+
+    /// Выполняет действие. Возвращает `false`, если для него нет обработчика.
+    pub fn dispatch(&mut self, name: &str, app_state: &mut AppState, ui: &mut AppUi) -> bool {
+        match self.handlers.get_mut(name) {
+            Some(handler) => {
+                handler(app_state, ui);
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,205 @@
+// src/ui/canvas.rs
+
+use crate::ui::widget::Rect;
+use image::{DynamicImage, GenericImageView, Pixel, Rgba};
+use rusttype::{point, Font, PositionedGlyph, Scale};
+
+/// Глифы с покрытием ниже этого порога не рисуются: так края букв не размываются в фон.
+const GLYPH_COVERAGE_THRESHOLD: f32 = 0.1;
+
+/// Холст поверх RGBA-буфера кадра. Все рисование идет через него: он знает размеры
+/// кадра, поэтому ничего не заезжает на соседнюю строку, а стек отсечения не дает
+/// виджету вылезать за свои границы. Координаты везде — координаты кадра.
+pub struct Canvas<'a> {
+    frame: &'a mut [u8],
+    width: u32,
+    height: u32,
+    /// Области отсечения в координатах кадра; каждая уже пересечена с предыдущей.
+    clips: Vec<Rect>,
+}
+
+impl<'a> Canvas<'a> {
+    /// Буфер должен вмещать `width * height` пикселей RGBA.
+    pub fn new(frame: &'a mut [u8], width: u32, height: u32) -> Self {
+        assert!(frame.len() >= (width as usize) * (height as usize) * 4, "frame buffer is smaller than {}x{}", width, height);
+        Self {
+            frame,
+            width,
+            height,
+            clips: vec![Rect::new(0, 0, width, height)],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Сужает область рисования до `rect` до парного `pop_clip`.
+    pub fn push_clip(&mut self, rect: Rect) {
+        let clip = intersect(self.current_clip(), rect);
+        self.clips.push(clip);
+    }
+
+    pub fn pop_clip(&mut self) {
+        // Нижний элемент — весь кадр, его не снимаем
+        if self.clips.len() > 1 {
+            self.clips.pop();
+        }
+    }
+
+    /// Заливает весь кадр, не глядя на отсечение.
+    pub fn clear(&mut self, color: [u8; 4]) {
+        self.frame.chunks_exact_mut(4).for_each(|pixel| pixel.copy_from_slice(&color));
+    }
+
+    /// Заливает прямоугольник цветом без смешивания.
+    pub fn fill(&mut self, rect: Rect, color: [u8; 4]) {
+        let Some((left, top, right, bottom)) = self.visible(rect) else {
+            return;
+        };
+        for y in top..bottom {
+            let row = (y as usize * self.width as usize) * 4;
+            for x in left..right {
+                let index = row + x as usize * 4;
+                self.frame[index..index + 4].copy_from_slice(&color);
+            }
+        }
+    }
+
+    /// Смешивает один пиксель с кадром по его альфе, умноженной на `alpha`.
+    pub fn blend(&mut self, x: i32, y: i32, pixel: Rgba<u8>, alpha: f32) {
+        let clip = self.current_clip();
+        if x < clip.x || y < clip.y || x >= clip.x + clip.width as i32 || y >= clip.y + clip.height as i32 {
+            return;
+        }
+        let index = (y as usize * self.width as usize + x as usize) * 4;
+        let mut new_pixel = pixel;
+        new_pixel.0[3] = (new_pixel.0[3] as f32 * alpha) as u8;
+
+        let old_pixel_slice = &mut self.frame[index..index + 4];
+        let mut old_pixel = Rgba([old_pixel_slice[0], old_pixel_slice[1], old_pixel_slice[2], old_pixel_slice[3]]);
+        old_pixel.blend(&new_pixel);
+        old_pixel_slice.copy_from_slice(&old_pixel.0);
+    }
+
+    /// Накладывает картинку левым верхним углом в `(x, y)` с прозрачностью `alpha`.
+    pub fn blit(&mut self, image: &DynamicImage, x: i32, y: i32, alpha: f32) {
+        // Почти прозрачную картинку не рисуем совсем
+        if alpha <= 0.01 {
+            return;
+        }
+        for (px, py, pixel) in image.pixels() {
+            self.blend(x + px as i32, y + py as i32, pixel, alpha);
+        }
+    }
+
+    /// Рисует разложенные глифы, смешивая их с фоном по покрытию.
+    pub fn glyphs<'g>(&mut self, glyphs: impl IntoIterator<Item = PositionedGlyph<'g>>, color: [u8; 4]) {
+        for glyph in glyphs {
+            let Some(bounding_box) = glyph.pixel_bounding_box() else {
+                continue;
+            };
+            glyph.draw(|gx, gy, v| {
+                if v > GLYPH_COVERAGE_THRESHOLD {
+                    let pixel = Rgba([color[0], color[1], color[2], (color[3] as f32 * v) as u8]);
+                    self.blend(bounding_box.min.x + gx as i32, bounding_box.min.y + gy as i32, pixel, 1.0);
+                }
+            });
+        }
+    }
+
+    /// Рисует строку, начиная с точки `(x, baseline)` на базовой линии.
+    pub fn text(&mut self, font: &Font, text: &str, scale: Scale, (x, baseline): (f32, f32), color: [u8; 4]) {
+        self.glyphs(font.layout(text, scale, point(x, baseline)), color);
+    }
+
+    fn current_clip(&self) -> Rect {
+        *self.clips.last().unwrap_or(&Rect::new(0, 0, self.width, self.height))
+    }
+
+    /// Видимая часть прямоугольника: `(left, top, right, bottom)`.
+    fn visible(&self, rect: Rect) -> Option<(i32, i32, i32, i32)> {
+        let rect = intersect(self.current_clip(), rect);
+        (rect.width > 0 && rect.height > 0).then(|| (rect.x, rect.y, rect.x + rect.width as i32, rect.y + rect.height as i32))
+    }
+}
+
+/// Пересечение прямоугольников; пустое пересечение — прямоугольник нулевого размера.
+fn intersect(a: Rect, b: Rect) -> Rect {
+    let left = a.x.max(b.x);
+    let top = a.y.max(b.y);
+    let right = (a.x + a.width as i32).min(b.x + b.width as i32);
+    let bottom = (a.y + a.height as i32).min(b.y + b.height as i32);
+    Rect::new(left, top, (right - left).max(0) as u32, (bottom - top).max(0) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+
+    /// Кадр `width` x `height`, залитый черным, и координаты закрашенных пикселей после `draw`.
+    fn painted(width: u32, height: u32, draw: impl FnOnce(&mut Canvas)) -> Vec<(u32, u32)> {
+        let mut frame = vec![0; (width * height * 4) as usize];
+        draw(&mut Canvas::new(&mut frame, width, height));
+        frame
+            .chunks_exact(4)
+            .enumerate()
+            .filter(|(_, pixel)| pixel[0] > 0)
+            .map(|(i, _)| (i as u32 % width, i as u32 / width))
+            .collect()
+    }
+
+    fn area(left: u32, top: u32, right: u32, bottom: u32) -> Vec<(u32, u32)> {
+        (top..bottom).flat_map(|y| (left..right).map(move |x| (x, y))).collect()
+    }
+
+    #[test]
+    fn nested_clips_intersect_and_pop_back() {
+        let pixels = painted(8, 8, |canvas| {
+            canvas.push_clip(Rect::new(1, 1, 5, 5));
+            canvas.push_clip(Rect::new(3, 0, 8, 3));
+            assert_eq!(canvas.current_clip(), Rect::new(3, 1, 3, 2));
+            canvas.fill(Rect::new(0, 0, 8, 8), RED);
+            canvas.pop_clip();
+            assert_eq!(canvas.current_clip(), Rect::new(1, 1, 5, 5));
+            canvas.pop_clip();
+            canvas.pop_clip();
+            assert_eq!(canvas.current_clip(), Rect::new(0, 0, 8, 8));
+        });
+        assert_eq!(pixels, area(3, 1, 6, 3));
+    }
+
+    #[test]
+    fn disjoint_clip_hides_everything() {
+        let pixels = painted(8, 8, |canvas| {
+            canvas.push_clip(Rect::new(0, 0, 3, 3));
+            canvas.push_clip(Rect::new(5, 5, 3, 3));
+            canvas.fill(Rect::new(0, 0, 8, 8), RED);
+            canvas.blend(6, 6, Rgba(RED), 1.0);
+        });
+        assert!(pixels.is_empty());
+    }
+
+    #[test]
+    fn right_edge_does_not_wrap_onto_the_next_row() {
+        let filled = painted(6, 4, |canvas| canvas.fill(Rect::new(4, 1, 5, 2), RED));
+        assert_eq!(filled, area(4, 1, 6, 3));
+
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(5, 2, Rgba(RED)));
+        let blitted = painted(6, 4, |canvas| canvas.blit(&image, 4, 1, 1.0));
+        assert_eq!(blitted, area(4, 1, 6, 3));
+
+        let clipped = painted(6, 4, |canvas| {
+            canvas.push_clip(Rect::new(0, 0, 5, 4));
+            canvas.blit(&image, 4, 1, 1.0);
+        });
+        assert_eq!(clipped, area(4, 1, 5, 3));
+    }
+}
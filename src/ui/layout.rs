@@ -0,0 +1,569 @@
+// src/ui/layout.rs
+
+use crate::ui::canvas::Canvas;
+use crate::ui::widget::{DrawContext, EventContext, Rect, UiEvent, Widget};
+use serde::Deserialize;
+use std::marker::PhantomData;
+
+/// Сколько места виджет просит вдоль одной оси.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Size {
+    /// Ровно столько пикселей.
+    Fixed(u32),
+    /// Все свободное место; то же, что `Portion(1)`.
+    Fill,
+    /// Доля свободного места, пропорциональная весу.
+    Portion(u32),
+}
+
+/// Выравнивание внутри выделенного места.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Align {
+    #[default]
+    Start,
+    Center,
+    End,
+}
+
+/// Ограничения размера одного дочернего виджета.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Constraints {
+    pub width: Size,
+    pub height: Size,
+    pub min: (u32, u32),
+    pub max: (u32, u32),
+}
+
+impl Constraints {
+    pub fn new(width: Size, height: Size) -> Self {
+        Self { width, height, min: (0, 0), max: (u32::MAX, u32::MAX) }
+    }
+
+    /// Виджет постоянного размера.
+    pub fn fixed(width: u32, height: u32) -> Self {
+        Self::new(Size::Fixed(width), Size::Fixed(height))
+    }
+
+    /// Виджет, занимающий все свободное место.
+    pub fn fill() -> Self {
+        Self::new(Size::Fill, Size::Fill)
+    }
+
+    pub fn with_min(mut self, width: u32, height: u32) -> Self {
+        self.min = (width, height);
+        self
+    }
+
+    pub fn with_max(mut self, width: u32, height: u32) -> Self {
+        self.max = (width, height);
+        self
+    }
+
+    fn size(&self, axis: Axis) -> Size {
+        match axis {
+            Axis::Horizontal => self.width,
+            Axis::Vertical => self.height,
+        }
+    }
+
+    fn clamp(&self, axis: Axis, value: u32) -> u32 {
+        let (min, max) = match axis {
+            Axis::Horizontal => (self.min.0, self.max.0),
+            Axis::Vertical => (self.min.1, self.max.1),
+        };
+        value.clamp(min, max.max(min))
+    }
+
+    /// Размер вдоль оси, на которой у виджета есть `available` пикселей.
+    fn resolve(&self, axis: Axis, available: u32) -> u32 {
+        match self.size(axis) {
+            Size::Fixed(size) => self.clamp(axis, size),
+            Size::Fill | Size::Portion(_) => self.clamp(axis, available),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+/// Ось, вдоль которой `Linear` раскладывает детей.
+pub trait LinearAxis: 'static {
+    const AXIS: Axis;
+}
+
+pub struct Horizontal;
+pub struct Vertical;
+
+impl LinearAxis for Horizontal {
+    const AXIS: Axis = Axis::Horizontal;
+}
+
+impl LinearAxis for Vertical {
+    const AXIS: Axis = Axis::Vertical;
+}
+
+/// Дети друг под другом.
+pub type Column = Linear<Vertical>;
+/// Дети в ряд слева направо.
+pub type Row = Linear<Horizontal>;
+
+/// Раскладывает детей вдоль одной оси: сначала виджеты постоянного размера,
+/// остаток делится между `Fill` и `Portion` по весам.
+pub struct Linear<A: LinearAxis> {
+    bounds: Rect,
+    padding: u32,
+    spacing: u32,
+    /// Выравнивание группы детей вдоль оси, если место осталось.
+    main_align: Align,
+    /// Выравнивание каждого ребенка поперек оси.
+    cross_align: Align,
+    children: Vec<Box<dyn Widget>>,
+    constraints: Vec<Constraints>,
+    axis: PhantomData<A>,
+}
+
+impl<A: LinearAxis> Linear<A> {
+    pub fn new() -> Self {
+        Self {
+            bounds: Rect::default(),
+            padding: 0,
+            spacing: 0,
+            main_align: Align::Start,
+            cross_align: Align::Start,
+            children: Vec::new(),
+            constraints: Vec::new(),
+            axis: PhantomData,
+        }
+    }
+
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_spacing(mut self, spacing: u32) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    pub fn with_align(mut self, main_align: Align, cross_align: Align) -> Self {
+        self.main_align = main_align;
+        self.cross_align = cross_align;
+        self
+    }
+
+    pub fn with_child(mut self, widget: impl Widget, constraints: Constraints) -> Self {
+        self.push(Box::new(widget), constraints);
+        self
+    }
+
+    pub fn push(&mut self, widget: Box<dyn Widget>, constraints: Constraints) {
+        self.children.push(widget);
+        self.constraints.push(constraints);
+    }
+}
+
+impl<A: LinearAxis> Default for Linear<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: LinearAxis> Widget for Linear<A> {
+    fn handle_event(&mut self, _event: &UiEvent, _ctx: &mut EventContext) {}
+
+    fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    fn layout(&mut self, bounds: Rect) {
+        self.bounds = bounds;
+        let inner = inset(bounds, self.padding);
+        let (main_len, cross_len) = split(inner, A::AXIS);
+
+        let gaps = self.spacing * self.children.len().saturating_sub(1) as u32;
+        let sizes = distribute(&self.constraints, A::AXIS, main_len.saturating_sub(gaps));
+        let used = sizes.iter().sum::<u32>() + gaps;
+        let mut offset = align_offset(self.main_align, main_len, used);
+
+        for ((child, constraints), main_size) in self.children.iter_mut().zip(&self.constraints).zip(sizes) {
+            let cross_size = constraints.resolve(cross(A::AXIS), cross_len);
+            let cross_offset = align_offset(self.cross_align, cross_len, cross_size);
+            child.layout(place(inner, A::AXIS, offset, main_size, cross_offset, cross_size));
+            offset += (main_size + self.spacing) as i32;
+        }
+    }
+
+    fn draw(&self, _canvas: &mut Canvas, _ctx: &DrawContext) {}
+
+    fn children(&self) -> &[Box<dyn Widget>] {
+        &self.children
+    }
+
+    fn children_mut(&mut self) -> &mut [Box<dyn Widget>] {
+        &mut self.children
+    }
+}
+
+/// Сетка: дети заполняют ячейки по строкам. Ширины колонок и высоты строк
+/// задаются так же, как размеры детей `Linear`.
+pub struct Grid {
+    bounds: Rect,
+    padding: u32,
+    spacing: u32,
+    columns: Vec<Size>,
+    /// Высоты строк; строкам сверх списка достается `Fill`.
+    rows: Vec<Size>,
+    align: (Align, Align),
+    children: Vec<Box<dyn Widget>>,
+    constraints: Vec<Constraints>,
+}
+
+impl Grid {
+    pub fn new(columns: Vec<Size>) -> Self {
+        Self {
+            bounds: Rect::default(),
+            padding: 0,
+            spacing: 0,
+            columns,
+            rows: Vec::new(),
+            align: (Align::Start, Align::Start),
+            children: Vec::new(),
+            constraints: Vec::new(),
+        }
+    }
+
+    pub fn with_rows(mut self, rows: Vec<Size>) -> Self {
+        self.rows = rows;
+        self
+    }
+
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_spacing(mut self, spacing: u32) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Выравнивание ребенка внутри ячейки по горизонтали и вертикали.
+    pub fn with_align(mut self, horizontal: Align, vertical: Align) -> Self {
+        self.align = (horizontal, vertical);
+        self
+    }
+
+    pub fn with_child(mut self, widget: impl Widget, constraints: Constraints) -> Self {
+        self.push(Box::new(widget), constraints);
+        self
+    }
+
+    pub fn push(&mut self, widget: Box<dyn Widget>, constraints: Constraints) {
+        self.children.push(widget);
+        self.constraints.push(constraints);
+    }
+}
+
+impl Widget for Grid {
+    fn handle_event(&mut self, _event: &UiEvent, _ctx: &mut EventContext) {}
+
+    fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    fn layout(&mut self, bounds: Rect) {
+        self.bounds = bounds;
+        if self.columns.is_empty() {
+            return;
+        }
+        let inner = inset(bounds, self.padding);
+        let row_count = self.children.len().div_ceil(self.columns.len());
+        let track = |sizes: &[Size], count: usize, available: u32| {
+            let tracks: Vec<Constraints> = (0..count)
+                .map(|i| {
+                    let size = sizes.get(i).copied().unwrap_or(Size::Fill);
+                    Constraints::new(size, size)
+                })
+                .collect();
+            let gaps = self.spacing * count.saturating_sub(1) as u32;
+            let lengths = distribute(&tracks, Axis::Horizontal, available.saturating_sub(gaps));
+            let mut starts = Vec::with_capacity(count);
+            let mut offset = 0;
+            for length in &lengths {
+                starts.push(offset);
+                offset += (length + self.spacing) as i32;
+            }
+            (starts, lengths)
+        };
+        let (column_starts, column_widths) = track(&self.columns, self.columns.len(), inner.width);
+        let (row_starts, row_heights) = track(&self.rows, row_count, inner.height);
+
+        for (index, (child, constraints)) in self.children.iter_mut().zip(&self.constraints).enumerate() {
+            let (row, column) = (index / self.columns.len(), index % self.columns.len());
+            let cell = Rect::new(
+                inner.x + column_starts[column],
+                inner.y + row_starts[row],
+                column_widths[column],
+                row_heights[row],
+            );
+            child.layout(align_in(cell, constraints, self.align));
+        }
+    }
+
+    fn draw(&self, _canvas: &mut Canvas, _ctx: &DrawContext) {}
+
+    fn children(&self) -> &[Box<dyn Widget>] {
+        &self.children
+    }
+
+    fn children_mut(&mut self) -> &mut [Box<dyn Widget>] {
+        &mut self.children
+    }
+}
+
+/// Все дети в одном и том же месте друг над другом, каждый со своим выравниванием.
+pub struct Stack {
+    bounds: Rect,
+    padding: u32,
+    children: Vec<Box<dyn Widget>>,
+    constraints: Vec<Constraints>,
+    aligns: Vec<(Align, Align)>,
+}
+
+impl Stack {
+    pub fn new() -> Self {
+        Self { bounds: Rect::default(), padding: 0, children: Vec::new(), constraints: Vec::new(), aligns: Vec::new() }
+    }
+
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Добавляет слой поверх предыдущих, выровненный по горизонтали и вертикали.
+    pub fn with_child(mut self, widget: impl Widget, constraints: Constraints, align: (Align, Align)) -> Self {
+        self.push(Box::new(widget), constraints, align);
+        self
+    }
+
+    pub fn push(&mut self, widget: Box<dyn Widget>, constraints: Constraints, align: (Align, Align)) {
+        self.children.push(widget);
+        self.constraints.push(constraints);
+        self.aligns.push(align);
+    }
+}
+
+impl Default for Stack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Widget for Stack {
+    fn handle_event(&mut self, _event: &UiEvent, _ctx: &mut EventContext) {}
+
+    fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    fn layout(&mut self, bounds: Rect) {
+        self.bounds = bounds;
+        let inner = inset(bounds, self.padding);
+        for ((child, constraints), align) in self.children.iter_mut().zip(&self.constraints).zip(&self.aligns) {
+            child.layout(align_in(inner, constraints, *align));
+        }
+    }
+
+    fn draw(&self, _canvas: &mut Canvas, _ctx: &DrawContext) {}
+
+    fn children(&self) -> &[Box<dyn Widget>] {
+        &self.children
+    }
+
+    fn children_mut(&mut self) -> &mut [Box<dyn Widget>] {
+        &mut self.children
+    }
+}
+
+/// Делит `available` пикселей вдоль оси. Виджеты, упершиеся в min/max, фиксируются,
+/// а остаток заново делится между остальными.
+fn distribute(constraints: &[Constraints], axis: Axis, available: u32) -> Vec<u32> {
+    let mut sizes = vec![0; constraints.len()];
+    let mut flexible = Vec::new();
+    let mut remaining = available;
+
+    for (i, constraint) in constraints.iter().enumerate() {
+        match constraint.size(axis) {
+            Size::Fixed(size) => {
+                sizes[i] = constraint.clamp(axis, size);
+                remaining = remaining.saturating_sub(sizes[i]);
+            }
+            Size::Fill => flexible.push((i, 1)),
+            Size::Portion(weight) => flexible.push((i, weight)),
+        }
+    }
+
+    while !flexible.is_empty() {
+        let total_weight: u32 = flexible.iter().map(|(_, weight)| *weight).sum::<u32>().max(1);
+        let share = |weight: u32| (remaining as u64 * weight as u64 / total_weight as u64) as u32;
+
+        // Сначала фиксируем тех, кому доля не подходит по ограничениям
+        let clamped: Vec<(usize, u32)> = flexible
+            .iter()
+            .filter_map(|&(i, weight)| {
+                let size = constraints[i].clamp(axis, share(weight));
+                (size != share(weight)).then_some((i, size))
+            })
+            .collect();
+        if clamped.is_empty() {
+            for &(i, weight) in &flexible {
+                sizes[i] = share(weight);
+            }
+            break;
+        }
+        for (i, size) in clamped {
+            sizes[i] = size;
+            remaining = remaining.saturating_sub(size);
+            flexible.retain(|&(j, _)| j != i);
+        }
+    }
+    sizes
+}
+
+fn inset(rect: Rect, padding: u32) -> Rect {
+    Rect::new(
+        rect.x + padding as i32,
+        rect.y + padding as i32,
+        rect.width.saturating_sub(2 * padding),
+        rect.height.saturating_sub(2 * padding),
+    )
+}
+
+fn cross(axis: Axis) -> Axis {
+    match axis {
+        Axis::Horizontal => Axis::Vertical,
+        Axis::Vertical => Axis::Horizontal,
+    }
+}
+
+/// Длина прямоугольника вдоль оси и поперек нее.
+fn split(rect: Rect, axis: Axis) -> (u32, u32) {
+    match axis {
+        Axis::Horizontal => (rect.width, rect.height),
+        Axis::Vertical => (rect.height, rect.width),
+    }
+}
+
+fn place(inner: Rect, axis: Axis, main_offset: i32, main_size: u32, cross_offset: i32, cross_size: u32) -> Rect {
+    match axis {
+        Axis::Horizontal => Rect::new(inner.x + main_offset, inner.y + cross_offset, main_size, cross_size),
+        Axis::Vertical => Rect::new(inner.x + cross_offset, inner.y + main_offset, cross_size, main_size),
+    }
+}
+
+fn align_offset(align: Align, available: u32, size: u32) -> i32 {
+    let free = available.saturating_sub(size) as i32;
+    match align {
+        Align::Start => 0,
+        Align::Center => free / 2,
+        Align::End => free,
+    }
+}
+
+/// Место ребенка внутри `area` с учетом его ограничений и выравнивания.
+fn align_in(area: Rect, constraints: &Constraints, (horizontal, vertical): (Align, Align)) -> Rect {
+    let width = constraints.resolve(Axis::Horizontal, area.width);
+    let height = constraints.resolve(Axis::Vertical, area.height);
+    Rect::new(
+        area.x + align_offset(horizontal, area.width, width),
+        area.y + align_offset(vertical, area.height, height),
+        width,
+        height,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Пустой контейнер вместо настоящего виджета: нужны только его границы после раскладки.
+    fn leaf() -> Column {
+        Column::new()
+    }
+
+    fn child_bounds(widget: &dyn Widget) -> Vec<Rect> {
+        widget.children().iter().map(|child| child.bounds()).collect()
+    }
+
+    #[test]
+    fn portions_share_what_fixed_children_leave() {
+        let constraints = [
+            Constraints::new(Size::Fixed(100), Size::Fill),
+            Constraints::new(Size::Portion(1), Size::Fill),
+            Constraints::new(Size::Portion(3), Size::Fill),
+        ];
+        assert_eq!(distribute(&constraints, Axis::Horizontal, 500), vec![100, 100, 300]);
+    }
+
+    #[test]
+    fn clamped_child_gives_leftover_to_the_rest() {
+        let capped = [Constraints::fill().with_max(50, u32::MAX), Constraints::fill(), Constraints::fill()];
+        assert_eq!(distribute(&capped, Axis::Horizontal, 300), vec![50, 125, 125]);
+
+        let floored = [Constraints::fill().with_min(0, 200), Constraints::fill()];
+        assert_eq!(distribute(&floored, Axis::Vertical, 300), vec![200, 100]);
+    }
+
+    #[test]
+    fn row_aligns_children_along_and_across_the_axis() {
+        let row = |main: Align, cross: Align| {
+            let mut row = Row::new()
+                .with_spacing(10)
+                .with_align(main, cross)
+                .with_child(leaf(), Constraints::fixed(100, 20))
+                .with_child(leaf(), Constraints::fixed(90, 40));
+            row.layout(Rect::new(0, 0, 400, 100));
+            child_bounds(&row)
+        };
+
+        assert_eq!(row(Align::Start, Align::Start), vec![Rect::new(0, 0, 100, 20), Rect::new(110, 0, 90, 40)]);
+        assert_eq!(row(Align::Center, Align::Center), vec![Rect::new(100, 40, 100, 20), Rect::new(210, 30, 90, 40)]);
+        assert_eq!(row(Align::End, Align::End), vec![Rect::new(200, 80, 100, 20), Rect::new(310, 60, 90, 40)]);
+    }
+
+    #[test]
+    fn grid_rows_beyond_the_list_fill_the_rest() {
+        let mut grid = Grid::new(vec![Size::Fixed(100), Size::Fill]).with_rows(vec![Size::Fixed(30)]).with_padding(10);
+        for _ in 0..6 {
+            grid.push(Box::new(leaf()), Constraints::fill());
+        }
+        grid.layout(Rect::new(0, 0, 320, 250));
+
+        assert_eq!(
+            child_bounds(&grid),
+            vec![
+                Rect::new(10, 10, 100, 30),
+                Rect::new(110, 10, 200, 30),
+                Rect::new(10, 40, 100, 100),
+                Rect::new(110, 40, 200, 100),
+                Rect::new(10, 140, 100, 100),
+                Rect::new(110, 140, 200, 100),
+            ]
+        );
+    }
+
+    #[test]
+    fn grid_aligns_children_inside_cells() {
+        let mut grid = Grid::new(vec![Size::Fill, Size::Fill]).with_spacing(20).with_align(Align::Center, Align::End);
+        grid.push(Box::new(leaf()), Constraints::fixed(40, 10));
+        grid.push(Box::new(leaf()), Constraints::fixed(40, 10));
+        grid.layout(Rect::new(0, 0, 220, 50));
+
+        assert_eq!(child_bounds(&grid), vec![Rect::new(30, 40, 40, 10), Rect::new(150, 40, 40, 10)]);
+    }
+}
@@ -1,111 +1,190 @@
 // src/ui/mod.rs
 
+pub mod action;
+pub mod canvas;
+pub mod layout;
+pub mod screen;
+pub mod skin;
+pub mod text_edit;
+pub mod text_input;
+pub mod widget;
 pub mod widgets;
 
-use crate::{ai_renderer::AiRenderer, AppState};
-use widgets::{Button, TextInput, TextPanel};
+use crate::{backend::{AssetSpec, ImageBackend}, clock::Clock, AppState};
+use screen::{ScreenDef, ScreenError};
+use canvas::Canvas;
+use widget::{draw_tree, visit, visit_mut, DrawContext, EventContext, PointerState, Rect, UiEvent, Widget};
+use widgets::Button;
 use rusttype::Font;
-use winit::event::{Event, KeyEvent, WindowEvent, Ime};
-use std::collections::HashMap;
-use image::DynamicImage;
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use image::{DynamicImage, RgbaImage};
 use std::sync::Arc;
+use std::time::Duration;
 
 /// Менеджер UI основного приложения.
 pub struct AppUi {
-    pub buttons: Vec<Button>,
-    pub text_input: TextInput,
-    pub text_panel: TextPanel,
+    /// Корень дерева виджетов; дети рисуются в порядке добавления.
+    /// Хранится срезом из одного элемента, чтобы обходить его как любой список детей.
+    widgets: [Box<dyn Widget>; 1],
     #[allow(dead_code)]
-    ai_renderer: Arc<AiRenderer>, // Сохраняем на случай будущих генераций
+    backend: Arc<dyn ImageBackend>, // Сохраняем на случай будущих генераций
     render_cache: HashMap<String, Arc<DynamicImage>>,
+    /// Время последнего кадра по часам приложения; с ним виджеты получают события
+    /// и картинки, пришедшие между кадрами.
+    now: Duration,
 }
 
 impl AppUi {
-    /// Создается с уже готовым, заполненным кэшем.
-    pub fn new(
-        font: Arc<Font<'static>>,
-        ai_renderer: Arc<AiRenderer>,
-        render_cache: HashMap<String, Arc<DynamicImage>>,
-    ) -> Self {
-        let buttons = vec![
-            // ИСПРАВЛЕНИЕ: Конвертируем &str в String
-            Button::new(0, 50, 50, 200, 60, "Submit".to_string(), Arc::clone(&font)),
-            Button::new(1, 270, 50, 150, 60, "Clear".to_string(), Arc::clone(&font)),
-        ];
-        let text_input = TextInput::new(50, 130, 370, 40, Arc::clone(&font));
-        let text_panel = TextPanel::new(50, 200, Arc::clone(&font));
+    /// Встроенный главный экран из `ui/main.json`: из него фаза загрузки берет список ассетов,
+    /// а потом этот же объект становится работающим UI.
+    pub fn new(font: Arc<Font<'static>>, backend: Arc<dyn ImageBackend>) -> Self {
+        Self::from_screen(&ScreenDef::builtin(), font, backend).expect("the built-in screen has unique button ids")
+    }
+
+    /// UI из файла описания экрана.
+    pub fn from_screen(screen: &ScreenDef, font: Arc<Font<'static>>, backend: Arc<dyn ImageBackend>) -> Result<Self, ScreenError> {
+        Ok(Self::with_root(backend, screen.build(font)?))
+    }
 
+    /// UI с произвольным корневым виджетом, обычно контейнером.
+    pub fn with_root(backend: Arc<dyn ImageBackend>, root: Box<dyn Widget>) -> Self {
         Self {
-            buttons,
-            text_input,
-            text_panel,
-            ai_renderer,
-            render_cache,
+            widgets: [root],
+            backend,
+            render_cache: HashMap::new(),
+            now: Duration::ZERO,
         }
     }
 
-    /// Обновляет состояние всех виджетов.
-    pub fn update(
-        &mut self,
-        app_state: &AppState,
-        mouse_clicked: bool,
-        event: &Event<()>,
-    ) -> Option<usize> {
-        // Фокус текстового поля
-        if mouse_clicked {
-            if self.text_input.is_over(app_state.mouse_pos) {
-                self.text_input.is_focused = true;
-            } else {
-                self.text_input.is_focused = false;
+    /// Раскладывает виджеты по окну размера `width`x`height`.
+    /// Вызывается при старте и при каждом изменении размера окна.
+    pub fn layout(&mut self, width: u32, height: u32) {
+        self.widgets[0].layout(Rect::new(0, 0, width, height));
+    }
+
+    /// Все ассеты, которые нужны виджетам, без повторов.
+    /// Виджеты с общим скином запрашивают одни и те же ассеты.
+    pub fn asset_specs(&self) -> Vec<AssetSpec> {
+        let mut specs = Vec::new();
+        visit(&self.widgets, &mut |widget| specs.extend(widget.asset_specs()));
+
+        let mut seen_keys = HashSet::new();
+        specs.retain(|spec| seen_keys.insert(spec.key.clone()));
+        specs
+    }
+
+    /// Отдает UI готовые картинки фонов.
+    pub fn set_render_cache(&mut self, render_cache: HashMap<String, Arc<DynamicImage>>) {
+        self.render_cache = render_cache;
+    }
+
+    /// Добавляет картинку, которая пришла уже после запуска UI.
+    /// Виджеты, которым она нужна, плавно переходят к ней от запасного фона.
+    pub fn insert_asset(&mut self, key: String, image: Arc<DynamicImage>) {
+        visit_mut(&mut self.widgets, &mut |widget| widget.on_asset_arrived(&key, self.now));
+        self.render_cache.insert(key, image);
+    }
+
+    /// Вызывает `f` для первого виджета типа `T` в дереве.
+    pub fn with_widget<T: Widget, R>(&mut self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        let mut f = Some(f);
+        let mut result = None;
+        visit_mut(&mut self.widgets, &mut |widget| {
+            let widget: &mut dyn Any = widget;
+            if let Some(found) = widget.downcast_mut::<T>() {
+                if let Some(f) = f.take() {
+                    result = Some(f(found));
+                }
             }
-        }
+        });
+        result
+    }
 
-        // Обработка ввода текста
-        if let Event::WindowEvent { event, .. } = event {
-            self.handle_key_event(event);
-        }
+    /// Где находится кнопка с действием `action`.
+    pub fn action_bounds(&self, action: &str) -> Option<Rect> {
+        let mut bounds = None;
+        visit(&self.widgets, &mut |widget| {
+            let widget: &dyn Any = widget;
+            if let Some(button) = widget.downcast_ref::<Button>() {
+                if bounds.is_none() && button.action() == Some(action) {
+                    bounds = Some(button.bounds());
+                }
+            }
+        });
+        bounds
+    }
 
-        let mut clicked_id = None;
-        for button in self.buttons.iter_mut() {
-            // `button.update` теперь возвращает `bool` только если был произведен КЛИК
-            if button.update(app_state.mouse_pos, app_state.mouse_pressed, mouse_clicked) {
-                clicked_id = Some(button.id);
-                // Если кликнули на кнопку, снимаем фокус с поля ввода
-                self.text_input.is_focused = false;
+    /// Где находится виджет, привязанный к полю состояния `binding`.
+    pub fn binding_bounds(&self, binding: &str) -> Option<Rect> {
+        let mut bounds = None;
+        visit(&self.widgets, &mut |widget| {
+            if bounds.is_none() && widget.binding() == Some(binding) {
+                bounds = Some(widget.bounds());
             }
-        }
+        });
+        bounds
+    }
 
-        clicked_id
+    /// Переносит в состояние приложения то, что пользователь изменил в привязанных виджетах.
+    pub fn read_bindings(&self, app_state: &mut AppState) {
+        visit(&self.widgets, &mut |widget| {
+            if let (Some(binding), Some(value)) = (widget.binding(), widget.value()) {
+                app_state.set_bound_value(binding, value);
+            }
+        });
     }
 
-    /// Обработка событий клавиатуры для текстового поля
-    fn handle_key_event(&mut self, event: &WindowEvent) {
-        if !self.text_input.is_focused { return; }
-        match event {
-            WindowEvent::KeyboardInput { event: KeyEvent { state, logical_key, .. }, .. } if *state == winit::event::ElementState::Pressed => {
-                use winit::keyboard::{Key, NamedKey};
-                match logical_key {
-                    Key::Named(NamedKey::Backspace) => self.text_input.backspace(),
-                    _ => {}
-                }
-            },
-            WindowEvent::Ime(Ime::Commit(text)) => {
-                self.text_input.key_press(text);
+    /// Показывает в привязанных виджетах текущие значения состояния приложения.
+    /// Виджет, который уже показывает это значение, не трогается: курсор и отмена в поле ввода сохраняются.
+    pub fn write_bindings(&mut self, app_state: &AppState) {
+        visit_mut(&mut self.widgets, &mut |widget| {
+            let Some(value) = widget.binding().and_then(|binding| app_state.bound_value(binding)) else { return };
+            if widget.value().as_ref() != Some(&value) {
+                widget.set_value(&value);
             }
-            _ => (),
-        }
+        });
+    }
+
+    /// Передает событие всем виджетам. Возвращает имена действий, которые оно вызвало.
+    pub fn handle_event(&mut self, app_state: &AppState, event: &UiEvent) -> Vec<String> {
+        let mut ctx = EventContext { pointer: pointer_state(app_state), now: self.now, actions: Vec::new() };
+        visit_mut(&mut self.widgets, &mut |widget| widget.handle_event(event, &mut ctx));
+        ctx.actions
+    }
+
+    /// Обновляет состояние всех виджетов. Вызывается каждый кадр; время кадра берется из `clock`.
+    pub fn update(&mut self, app_state: &AppState, clock: &dyn Clock) {
+        self.now = clock.now();
+        let (pointer, now) = (pointer_state(app_state), self.now);
+        visit_mut(&mut self.widgets, &mut |widget| widget.update(pointer, now));
     }
-    
+
+    /// Доводит все анимации до конца.
+    pub fn settle(&mut self) {
+        visit_mut(&mut self.widgets, &mut |widget| widget.settle());
+    }
+
+    /// Рисует UI без окна в новый RGBA-буфер `width` x `height`, заново разложив виджеты под этот размер.
+    pub fn render(&mut self, app_state: &AppState, clock: &dyn Clock, width: u32, height: u32) -> RgbaImage {
+        self.layout(width, height);
+        let mut image = RgbaImage::new(width, height);
+        let mut canvas = Canvas::new(&mut image, width, height);
+        canvas.clear(app_state.bg_color);
+        self.draw(app_state, &mut canvas, clock);
+        image
+    }
+
     /// Отрисовка всех виджетов.
-    pub fn draw(&mut self, app_state: &AppState, frame: &mut [u8], screen_width: u32) {
-        for button in &self.buttons {
-            // Передаем кэш в каждый виджет для отрисовки
-            button.draw(frame, screen_width, &self.render_cache);
-        }
+    pub fn draw(&mut self, app_state: &AppState, canvas: &mut Canvas, clock: &dyn Clock) {
+        self.write_bindings(app_state);
 
-        let final_message = format!("{} (Clicks: {})", app_state.message, app_state.click_count);
-        self.text_panel.draw(&final_message, frame, screen_width);
-        
-        self.text_input.draw(frame, screen_width);
+        // Передаем кэш в каждый виджет для отрисовки
+        let ctx = DrawContext { now: clock.now(), render_cache: &self.render_cache };
+        draw_tree(&self.widgets, canvas, &ctx);
     }
-}
\ No newline at end of file
+}
+
+fn pointer_state(app_state: &AppState) -> PointerState {
+    PointerState { pos: app_state.mouse_pos, pressed: app_state.mouse_pressed }
+}
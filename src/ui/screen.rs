@@ -0,0 +1,536 @@
+// src/ui/screen.rs
+
+use crate::ui::layout::{Align, Column, Constraints, Grid, Row, Size, Stack};
+use crate::ui::skin::{Insets, NineSliceSkin};
+use crate::ui::widget::Widget;
+use crate::ui::text_input::TextInput;
+use crate::ui::widgets::{Button, TextPanel};
+use crate::AppState;
+use rusttype::Font;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Файл описания главного экрана по умолчанию.
+pub const DEFAULT_SCREEN_FILE: &str = "ui/main.json";
+
+/// Встроенный главный экран: тот же файл, вшитый в программу, чтобы не держать второе описание в коде.
+const BUILTIN_SCREEN: &str = include_str!("../../ui/main.json");
+
+/// Как часто проверять, не изменился ли файл описания.
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Описание экрана, загруженное из JSON: дерево виджетов с раскладкой, подписями,
+/// промптами и именами действий.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScreenDef {
+    pub root: WidgetDef,
+}
+
+/// Один узел дерева; вид виджета задается полем `type`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WidgetDef {
+    Column(LinearDef),
+    Row(LinearDef),
+    Grid(GridDef),
+    Stack(StackDef),
+    Button(ButtonDef),
+    TextInput(TextInputDef),
+    TextPanel(TextPanelDef),
+}
+
+/// Размер вдоль оси: число пикселей, `"fill"` или `{"portion": N}`.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum SizeDef {
+    Fixed(u32),
+    Keyword(SizeKeyword),
+    Portion { portion: u32 },
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeKeyword {
+    Fill,
+}
+
+impl Default for SizeDef {
+    fn default() -> Self {
+        SizeDef::Keyword(SizeKeyword::Fill)
+    }
+}
+
+impl From<SizeDef> for Size {
+    fn from(size: SizeDef) -> Self {
+        match size {
+            SizeDef::Fixed(pixels) => Size::Fixed(pixels),
+            SizeDef::Keyword(SizeKeyword::Fill) => Size::Fill,
+            SizeDef::Portion { portion } => Size::Portion(portion),
+        }
+    }
+}
+
+/// Место виджета внутри родителя. Общее для всех узлов.
+#[derive(Debug, Default, Deserialize)]
+pub struct Placement {
+    #[serde(default)]
+    pub width: SizeDef,
+    #[serde(default)]
+    pub height: SizeDef,
+    #[serde(default)]
+    pub min_width: u32,
+    #[serde(default)]
+    pub min_height: u32,
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    /// Выравнивание по горизонтали и вертикали внутри `stack`.
+    #[serde(default)]
+    pub align: (Align, Align),
+}
+
+impl Placement {
+    fn constraints(&self) -> Constraints {
+        Constraints::new(self.width.into(), self.height.into())
+            .with_min(self.min_width, self.min_height)
+            .with_max(self.max_width.unwrap_or(u32::MAX), self.max_height.unwrap_or(u32::MAX))
+    }
+
+    /// Начальный размер виджета до раскладки. Кнопки без скина генерируются
+    /// ровно под него, поэтому им нужен фиксированный размер (см. `is_fixed`).
+    fn initial_size(&self, default: (u32, u32)) -> (u32, u32) {
+        let pick = |size: SizeDef, min: u32, max: Option<u32>, fallback: u32| match size {
+            // Раскладка тоже ограничит фиксированный размер min/max
+            SizeDef::Fixed(pixels) => pixels.clamp(min, max.unwrap_or(u32::MAX).max(min)),
+            _ => fallback.max(min),
+        };
+        (
+            pick(self.width, self.min_width, self.max_width, default.0),
+            pick(self.height, self.min_height, self.max_height, default.1),
+        )
+    }
+
+    /// Оба размера заданы в пикселях: после раскладки виджет останется размера `initial_size`.
+    fn is_fixed(&self) -> bool {
+        matches!((self.width, self.height), (SizeDef::Fixed(_), SizeDef::Fixed(_)))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LinearDef {
+    #[serde(flatten)]
+    pub placement: Placement,
+    #[serde(default)]
+    pub padding: u32,
+    #[serde(default)]
+    pub spacing: u32,
+    #[serde(default)]
+    pub main_align: Align,
+    #[serde(default)]
+    pub cross_align: Align,
+    #[serde(default)]
+    pub children: Vec<WidgetDef>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GridDef {
+    #[serde(flatten)]
+    pub placement: Placement,
+    pub columns: Vec<SizeDef>,
+    #[serde(default)]
+    pub rows: Vec<SizeDef>,
+    #[serde(default)]
+    pub padding: u32,
+    #[serde(default)]
+    pub spacing: u32,
+    /// Выравнивание детей в ячейках по горизонтали и вертикали.
+    #[serde(default)]
+    pub cell_align: (Align, Align),
+    #[serde(default)]
+    pub children: Vec<WidgetDef>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StackDef {
+    #[serde(flatten)]
+    pub placement: Placement,
+    #[serde(default)]
+    pub padding: u32,
+    #[serde(default)]
+    pub children: Vec<WidgetDef>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ButtonDef {
+    #[serde(flatten)]
+    pub placement: Placement,
+    /// Id кнопки, он же префикс ключей ее ассетов; по умолчанию — порядковый номер.
+    pub id: Option<usize>,
+    pub label: String,
+    pub prompt: Option<String>,
+    pub action: Option<String>,
+    pub seed: Option<i64>,
+    pub skin: Option<SkinDef>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TextInputDef {
+    #[serde(flatten)]
+    pub placement: Placement,
+    pub skin: Option<SkinDef>,
+    pub placeholder: Option<String>,
+    /// Скрывать ввод маской.
+    #[serde(default)]
+    pub password: bool,
+    /// Наибольшая длина текста в графемах.
+    pub max_length: Option<usize>,
+    /// Поле `AppState`, в которое пишется текст, например `"input"`.
+    pub bind: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TextPanelDef {
+    #[serde(flatten)]
+    pub placement: Placement,
+    /// Поле `AppState`, которое показывает панель, например `"status"`.
+    pub bind: Option<String>,
+}
+
+/// Nine-slice скин. Незаданные поля берутся из `NineSliceSkin::default_panel`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkinDef {
+    pub name: String,
+    pub prompt: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Толщина краев, одинаковая со всех сторон.
+    pub insets: Option<u32>,
+}
+
+impl SkinDef {
+    fn build(&self) -> Arc<NineSliceSkin> {
+        let default = NineSliceSkin::default_panel();
+        Arc::new(NineSliceSkin::new(
+            &self.name,
+            self.prompt.as_deref().unwrap_or(&default.prompt),
+            self.width.unwrap_or(default.width),
+            self.height.unwrap_or(default.height),
+            self.insets.map(Insets::uniform).unwrap_or(default.insets),
+        ))
+    }
+}
+
+/// Ошибка чтения файла описания экрана.
+#[derive(Debug)]
+pub enum ScreenError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, serde_json::Error),
+    /// Две кнопки с одним id делили бы ключи ассетов.
+    DuplicateButtonId(usize),
+    /// Виджет привязан к полю, которого нет в `AppState::BINDINGS`.
+    UnknownBinding(String),
+    /// Кнопка без скина рисует картинку ровно своего размера, поэтому ее размер
+    /// должен быть задан в пикселях, а не `fill` или `portion`.
+    UnsizedButton(usize),
+}
+
+impl fmt::Display for ScreenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScreenError::Io(path, e) => write!(f, "failed to read '{}': {}", path.display(), e),
+            ScreenError::Parse(path, e) => write!(f, "invalid screen description '{}': {}", path.display(), e),
+            ScreenError::DuplicateButtonId(id) => write!(f, "invalid screen description: button id {} is used more than once", id),
+            ScreenError::UnknownBinding(name) => {
+                write!(f, "invalid screen description: unknown binding '{}', expected one of {:?}", name, AppState::BINDINGS)
+            }
+            ScreenError::UnsizedButton(id) => {
+                write!(f, "invalid screen description: button {} has no skin, so its width and height must be fixed pixel sizes", id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScreenError {}
+
+impl ScreenDef {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScreenError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| ScreenError::Io(path.to_path_buf(), e))?;
+        serde_json::from_str(&text).map_err(|e| ScreenError::Parse(path.to_path_buf(), e))
+    }
+
+    /// Встроенный главный экран из `ui/main.json`.
+    pub fn builtin() -> Self {
+        serde_json::from_str(BUILTIN_SCREEN).expect("the built-in screen description is valid")
+    }
+
+    /// Строит дерево виджетов по описанию.
+    pub fn build(&self, font: Arc<Font<'static>>) -> Result<Box<dyn Widget>, ScreenError> {
+        let mut ids = ButtonIds { next: 0, used: HashSet::new() };
+        build_widget(&self.root, &font, &mut ids)
+    }
+}
+
+/// Id кнопок, уже занятые при построении экрана.
+struct ButtonIds {
+    /// Id для следующей кнопки без явного id.
+    next: usize,
+    used: HashSet<usize>,
+}
+
+fn build_widget(def: &WidgetDef, font: &Arc<Font<'static>>, ids: &mut ButtonIds) -> Result<Box<dyn Widget>, ScreenError> {
+    match def {
+        WidgetDef::Column(linear) => {
+            let mut column = Column::new()
+                .with_padding(linear.padding)
+                .with_spacing(linear.spacing)
+                .with_align(linear.main_align, linear.cross_align);
+            for child in &linear.children {
+                column.push(build_widget(child, font, ids)?, child.placement().constraints());
+            }
+            Ok(Box::new(column))
+        }
+        WidgetDef::Row(linear) => {
+            let mut row = Row::new()
+                .with_padding(linear.padding)
+                .with_spacing(linear.spacing)
+                .with_align(linear.main_align, linear.cross_align);
+            for child in &linear.children {
+                row.push(build_widget(child, font, ids)?, child.placement().constraints());
+            }
+            Ok(Box::new(row))
+        }
+        WidgetDef::Grid(grid_def) => {
+            let columns = grid_def.columns.iter().map(|&size| size.into()).collect();
+            let rows = grid_def.rows.iter().map(|&size| size.into()).collect();
+            let mut grid = Grid::new(columns)
+                .with_rows(rows)
+                .with_padding(grid_def.padding)
+                .with_spacing(grid_def.spacing)
+                .with_align(grid_def.cell_align.0, grid_def.cell_align.1);
+            for child in &grid_def.children {
+                grid.push(build_widget(child, font, ids)?, child.placement().constraints());
+            }
+            Ok(Box::new(grid))
+        }
+        WidgetDef::Stack(stack_def) => {
+            let mut stack = Stack::new().with_padding(stack_def.padding);
+            for child in &stack_def.children {
+                let placement = child.placement();
+                stack.push(build_widget(child, font, ids)?, placement.constraints(), placement.align);
+            }
+            Ok(Box::new(stack))
+        }
+        WidgetDef::Button(button_def) => {
+            let id = button_def.id.unwrap_or(ids.next);
+            if !ids.used.insert(id) {
+                return Err(ScreenError::DuplicateButtonId(id));
+            }
+            ids.next = id + 1;
+            if button_def.skin.is_none() && !button_def.placement.is_fixed() {
+                return Err(ScreenError::UnsizedButton(id));
+            }
+            let (width, height) = button_def.placement.initial_size((200, 60));
+            let mut button = Button::new(id, 0, 0, width, height, button_def.label.clone(), Arc::clone(font));
+            if let Some(prompt) = &button_def.prompt {
+                button = button.with_prompt(prompt);
+            }
+            if let Some(action) = &button_def.action {
+                button = button.with_action(action);
+            }
+            if let Some(seed) = button_def.seed {
+                button = button.with_seed(seed);
+            }
+            if let Some(skin) = &button_def.skin {
+                button = button.with_skin(skin.build());
+            }
+            Ok(Box::new(button))
+        }
+        WidgetDef::TextInput(input_def) => {
+            let (width, height) = input_def.placement.initial_size((370, 40));
+            let mut input = TextInput::new(0, 0, width, height, Arc::clone(font)).with_password(input_def.password);
+            if let Some(skin) = &input_def.skin {
+                input = input.with_skin(skin.build());
+            }
+            if let Some(placeholder) = &input_def.placeholder {
+                input = input.with_placeholder(placeholder);
+            }
+            if let Some(max_length) = input_def.max_length {
+                input = input.with_max_length(max_length);
+            }
+            if let Some(binding) = &input_def.bind {
+                input = input.with_binding(checked_binding(binding)?);
+            }
+            Ok(Box::new(input))
+        }
+        WidgetDef::TextPanel(panel_def) => {
+            let mut panel = TextPanel::new(0, 0, Arc::clone(font));
+            if let Some(binding) = &panel_def.bind {
+                panel = panel.with_binding(checked_binding(binding)?);
+            }
+            Ok(Box::new(panel))
+        }
+    }
+}
+
+fn checked_binding(binding: &str) -> Result<&str, ScreenError> {
+    if AppState::BINDINGS.contains(&binding) {
+        Ok(binding)
+    } else {
+        Err(ScreenError::UnknownBinding(binding.to_string()))
+    }
+}
+
+impl WidgetDef {
+    pub fn placement(&self) -> &Placement {
+        match self {
+            WidgetDef::Column(def) | WidgetDef::Row(def) => &def.placement,
+            WidgetDef::Grid(def) => &def.placement,
+            WidgetDef::Stack(def) => &def.placement,
+            WidgetDef::Button(def) => &def.placement,
+            WidgetDef::TextInput(def) => &def.placement,
+            WidgetDef::TextPanel(def) => &def.placement,
+        }
+    }
+}
+
+/// Следит за файлом описания и перечитывает его, когда он меняется на диске.
+pub struct ScreenWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+}
+
+impl ScreenWatcher {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let modified = modified_time(&path);
+        Self { path, modified, last_check: Instant::now() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Возвращает новое описание, если файл изменился с прошлой проверки.
+    /// Не блокирует и не трогает диск чаще, чем раз в `RELOAD_CHECK_INTERVAL`.
+    pub fn poll(&mut self) -> Option<Result<ScreenDef, ScreenError>> {
+        if self.last_check.elapsed() < RELOAD_CHECK_INTERVAL {
+            return None;
+        }
+        self.last_check = Instant::now();
+
+        let modified = modified_time(&self.path);
+        if modified == self.modified || modified.is_none() {
+            return None;
+        }
+        self.modified = modified;
+        Some(ScreenDef::load(&self.path))
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{ACTION_CLEAR, ACTION_SUBMIT};
+    use crate::backend::memory::MemoryBackend;
+    use crate::ui::widget::Rect;
+    use crate::ui::AppUi;
+
+    fn font() -> Arc<Font<'static>> {
+        Arc::new(Font::try_from_bytes(include_bytes!("../../assets/font.ttf")).unwrap())
+    }
+
+    fn parse(json: &str) -> Result<ScreenDef, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    #[test]
+    fn main_json_matches_builtin_screen() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(DEFAULT_SCREEN_FILE);
+        let screen = ScreenDef::load(&path).unwrap();
+        let from_file = AppUi::from_screen(&screen, font(), Arc::new(MemoryBackend::new())).unwrap();
+        let mut builtin = AppUi::new(font(), Arc::new(MemoryBackend::new()));
+
+        assert_eq!(from_file.asset_specs(), builtin.asset_specs());
+        builtin.layout(800, 600);
+        assert!(builtin.action_bounds(ACTION_SUBMIT).is_some());
+        assert!(builtin.action_bounds(ACTION_CLEAR).is_some());
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        assert!(parse(r#"{ "root": { "type": "text_panel" } }"#).is_ok());
+        // Опечатка в поле самого виджета, в поле раскладки и в скине
+        assert!(parse(r#"{ "root": { "type": "button", "label": "Ok", "acton": "submit" } }"#).is_err());
+        assert!(parse(r#"{ "root": { "type": "text_panel", "heigth": 50 } }"#).is_err());
+        assert!(parse(r#"{ "root": { "type": "text_input", "skin": { "name": "panel", "inset": 8 } } }"#).is_err());
+        assert!(parse(r#"{ "root": { "type": "text_panel" }, "theme": "dark" }"#).is_err());
+    }
+
+    #[test]
+    fn bindings_must_name_app_state_fields() {
+        let build = |bind: &str| {
+            let screen = parse(&format!(r#"{{ "root": {{ "type": "text_panel", "bind": "{}" }} }}"#, bind)).unwrap();
+            screen.build(font()).map(|panel| panel.binding().map(str::to_string))
+        };
+
+        assert_eq!(build(AppState::BIND_STATUS).unwrap().as_deref(), Some(AppState::BIND_STATUS));
+        assert!(matches!(build("stauts"), Err(ScreenError::UnknownBinding(name)) if name == "stauts"));
+    }
+
+    #[test]
+    fn duplicate_button_ids_are_rejected() {
+        let build = |buttons: &str| {
+            let buttons = buttons.replace(r#""type": "button","#, r#""type": "button", "width": 100, "height": 40,"#);
+            let screen = parse(&format!(r#"{{ "root": {{ "type": "row", "children": [{}] }} }}"#, buttons)).unwrap();
+            screen.build(font()).map(|_| ())
+        };
+
+        assert!(build(r#"{ "type": "button", "label": "A" }, { "type": "button", "label": "B" }"#).is_ok());
+        assert!(matches!(
+            build(r#"{ "type": "button", "id": 3, "label": "A" }, { "type": "button", "id": 3, "label": "B" }"#),
+            Err(ScreenError::DuplicateButtonId(3))
+        ));
+        // Автоматический id следует за предыдущим и тоже может столкнуться с явным
+        assert!(matches!(
+            build(r#"{ "type": "button", "id": 1, "label": "A" }, { "type": "button", "id": 0, "label": "B" }, { "type": "button", "label": "C" }"#),
+            Err(ScreenError::DuplicateButtonId(1))
+        ));
+    }
+
+    #[test]
+    fn buttons_without_skin_need_fixed_sizes() {
+        let build = |button: &str| parse(&format!(r#"{{ "root": {} }}"#, button)).unwrap().build(font()).map(|button| button.bounds());
+
+        assert_eq!(
+            build(r#"{ "type": "button", "label": "Ok", "width": 120, "height": 30, "max_width": 100 }"#).unwrap(),
+            Rect::new(0, 0, 100, 30)
+        );
+        assert!(matches!(
+            build(r#"{ "type": "button", "id": 4, "label": "Ok", "height": 30 }"#),
+            Err(ScreenError::UnsizedButton(4))
+        ));
+        assert!(matches!(
+            build(r#"{ "type": "button", "label": "Ok", "width": 120, "height": { "portion": 2 } }"#),
+            Err(ScreenError::UnsizedButton(0))
+        ));
+        // Nine-slice скин растягивается под любой размер
+        assert!(build(r#"{ "type": "button", "label": "Ok", "skin": { "name": "panel" } }"#).is_ok());
+    }
+}
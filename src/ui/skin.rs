@@ -0,0 +1,101 @@
+// src/ui/skin.rs
+
+use crate::backend::AssetSpec;
+use crate::ui::widgets::VisualState;
+use serde::{Deserialize, Serialize};
+
+/// Толщина неизменяемых краев nine-slice картинки в пикселях.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Insets {
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+}
+
+impl Insets {
+    pub fn uniform(size: u32) -> Self {
+        Self { left: size, top: size, right: size, bottom: size }
+    }
+}
+
+/// Как заполнять растягиваемые части nine-slice картинки.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SliceMode {
+    Stretch,
+    Tile,
+}
+
+/// Масштабируемый AI-скин: одна картинка на состояние, которая рисуется на виджете любого
+/// размера. Углы остаются как есть, края и центр растягиваются или повторяются.
+#[derive(Clone, Debug)]
+pub struct NineSliceSkin {
+    /// Имя скина; несколько виджетов с одним именем делят одни и те же ассеты.
+    pub name: String,
+    pub prompt: String,
+    /// Размер, в котором генерируется исходная картинка.
+    pub width: u32,
+    pub height: u32,
+    pub insets: Insets,
+    pub edges: SliceMode,
+    pub center: SliceMode,
+}
+
+impl NineSliceSkin {
+    pub fn new(name: &str, prompt: &str, width: u32, height: u32, insets: Insets) -> Self {
+        Self {
+            name: name.to_string(),
+            prompt: prompt.to_string(),
+            width,
+            height,
+            insets,
+            edges: SliceMode::Stretch,
+            center: SliceMode::Stretch,
+        }
+    }
+
+    pub fn with_modes(mut self, edges: SliceMode, center: SliceMode) -> Self {
+        self.edges = edges;
+        self.center = center;
+        self
+    }
+
+    /// Скин по умолчанию для полей ввода и панелей: тот же стиль, что у кнопок.
+    pub fn default_panel() -> Self {
+        Self::new(
+            "panel",
+            "a UI panel frame, thin glowing neon blue border, plain dark flat center, no text, dark sci-fi style, symmetrical",
+            256,
+            128,
+            Insets::uniform(16),
+        )
+    }
+
+    /// Логический ключ ассета скина в кэше, например `skin-panel-Idle`.
+    pub fn asset_key(&self, state: VisualState) -> String {
+        format!("skin-{}-{:?}", self.name, state)
+    }
+
+    /// Описания фонов скина для всех состояний. Hovered и Pressed выводятся из Idle.
+    pub fn asset_specs(&self) -> Vec<AssetSpec> {
+        let suffixes = [
+            (VisualState::Idle, "normal state"),
+            (VisualState::Hovered, "glowing, highlighted"),
+            (VisualState::Pressed, "pressed down, dimmed"),
+        ];
+        suffixes
+            .into_iter()
+            .map(|(state, suffix)| {
+                let spec = AssetSpec::new(self.asset_key(state), format!("{}, {}", self.prompt, suffix), self.width, self.height)
+                    .with_state(state)
+                    .with_widget(format!("skin-{}", self.name))
+                    .with_nine_slice(self.insets);
+                if state == VisualState::Idle {
+                    spec
+                } else {
+                    spec.derived_from(self.asset_key(VisualState::Idle))
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,432 @@
+// src/ui/text_edit.rs
+
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Сколько шагов отмены хранить.
+const UNDO_LIMIT: usize = 100;
+
+/// Состояние, которое запоминается для отмены.
+#[derive(Clone, Debug)]
+struct Snapshot {
+    text: String,
+    caret: usize,
+    anchor: usize,
+}
+
+/// Вид последней правки: подряд идущие правки одного вида отменяются одним шагом.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EditKind {
+    Insert,
+    Delete,
+}
+
+/// Модель редактирования однострочного текста. Все позиции — смещения в байтах,
+/// и они всегда стоят на границах графем, поэтому курсор не разрезает
+/// составные эмодзи и буквы с диакритикой.
+#[derive(Clone, Debug, Default)]
+pub struct TextEditor {
+    text: String,
+    caret: usize,
+    /// Второй конец выделения; равен `caret`, если ничего не выделено.
+    anchor: usize,
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    last_edit: Option<EditKind>,
+    /// Наибольшая длина текста в графемах.
+    max_length: Option<usize>,
+}
+
+impl TextEditor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn caret(&self) -> usize {
+        self.caret
+    }
+
+    /// Ограничивает длину текста; лишнее при вставке отбрасывается.
+    pub fn set_max_length(&mut self, max_length: Option<usize>) {
+        self.max_length = max_length;
+    }
+
+    /// Выделенный диапазон, если он не пустой.
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let range = self.caret.min(self.anchor)..self.caret.max(self.anchor);
+        (!range.is_empty()).then_some(range)
+    }
+
+    /// Заменяет весь текст; замену можно отменить.
+    pub fn set_text(&mut self, text: &str) {
+        let text = truncate(text, self.max_length);
+        if text == self.text {
+            return;
+        }
+        self.record(None);
+        self.text = text.to_string();
+        self.caret = self.text.len();
+        self.anchor = self.caret;
+    }
+
+    /// Вставляет текст на место выделения или в позицию курсора.
+    pub fn insert(&mut self, text: &str) {
+        let range = self.selection().unwrap_or(self.caret..self.caret);
+        // Сколько графем еще помещается, если выделение будет заменено
+        let room = self.max_length.map(|max| {
+            let kept = self.text[..range.start].graphemes(true).count() + self.text[range.end..].graphemes(true).count();
+            max.saturating_sub(kept)
+        });
+        let text = truncate(text, room);
+        if text.is_empty() {
+            return;
+        }
+        self.record(Some(EditKind::Insert));
+        self.text.replace_range(range.clone(), text);
+        self.caret = range.start + text.len();
+        self.anchor = self.caret;
+    }
+
+    /// Удаляет выделение или графему (слово, если `word`) перед курсором.
+    pub fn backspace(&mut self, word: bool) {
+        let range = self.selection().unwrap_or_else(|| {
+            let start = if word { self.prev_word(self.caret) } else { self.prev_boundary(self.caret) };
+            start..self.caret
+        });
+        self.delete_range(range);
+    }
+
+    /// Удаляет выделение или графему (слово, если `word`) после курсора.
+    pub fn delete(&mut self, word: bool) {
+        let range = self.selection().unwrap_or_else(|| {
+            let end = if word { self.next_word(self.caret) } else { self.next_boundary(self.caret) };
+            self.caret..end
+        });
+        self.delete_range(range);
+    }
+
+    pub fn move_left(&mut self, select: bool, word: bool) {
+        // Без Shift стрелка просто схлопывает выделение к его краю
+        let target = match self.selection() {
+            Some(range) if !select => range.start,
+            _ if word => self.prev_word(self.caret),
+            _ => self.prev_boundary(self.caret),
+        };
+        self.set_caret(target, select);
+    }
+
+    pub fn move_right(&mut self, select: bool, word: bool) {
+        let target = match self.selection() {
+            Some(range) if !select => range.end,
+            _ if word => self.next_word(self.caret),
+            _ => self.next_boundary(self.caret),
+        };
+        self.set_caret(target, select);
+    }
+
+    pub fn home(&mut self, select: bool) {
+        self.set_caret(0, select);
+    }
+
+    pub fn end(&mut self, select: bool) {
+        self.set_caret(self.text.len(), select);
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = 0;
+        self.caret = self.text.len();
+        self.last_edit = None;
+    }
+
+    /// Ставит курсор в `position` (округляя до границы графемы). С `select`
+    /// выделение тянется от прежнего якоря.
+    pub fn set_caret(&mut self, position: usize, select: bool) {
+        self.caret = self.snap(position);
+        if !select {
+            self.anchor = self.caret;
+        }
+        self.last_edit = None;
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(snapshot) = self.undo.pop() {
+            self.redo.push(self.snapshot());
+            self.restore(snapshot);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(snapshot) = self.redo.pop() {
+            self.undo.push(self.snapshot());
+            self.restore(snapshot);
+        }
+    }
+
+    /// Границы графем текста, включая `0` и `text.len()`.
+    pub fn boundaries(&self) -> Vec<usize> {
+        self.text
+            .grapheme_indices(true)
+            .map(|(index, _)| index)
+            .chain(std::iter::once(self.text.len()))
+            .collect()
+    }
+
+    fn delete_range(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        self.record(Some(EditKind::Delete));
+        self.text.replace_range(range.clone(), "");
+        self.caret = range.start;
+        self.anchor = self.caret;
+    }
+
+    /// Запоминает состояние перед правкой. Правки одного вида подряд сливаются в один шаг.
+    fn record(&mut self, kind: Option<EditKind>) {
+        if kind.is_none() || kind != self.last_edit {
+            self.undo.push(self.snapshot());
+            if self.undo.len() > UNDO_LIMIT {
+                self.undo.remove(0);
+            }
+        }
+        self.redo.clear();
+        self.last_edit = kind;
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot { text: self.text.clone(), caret: self.caret, anchor: self.anchor }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.text = snapshot.text;
+        self.caret = snapshot.caret;
+        self.anchor = snapshot.anchor;
+        self.last_edit = None;
+    }
+
+    /// Ближайшая граница графемы не правее `position`.
+    fn snap(&self, position: usize) -> usize {
+        let position = position.min(self.text.len());
+        self.boundaries().into_iter().rev().find(|&boundary| boundary <= position).unwrap_or(0)
+    }
+
+    fn prev_boundary(&self, position: usize) -> usize {
+        self.boundaries().into_iter().rev().find(|&boundary| boundary < position).unwrap_or(0)
+    }
+
+    fn next_boundary(&self, position: usize) -> usize {
+        self.boundaries().into_iter().find(|&boundary| boundary > position).unwrap_or(self.text.len())
+    }
+
+    /// Начало слова слева от `position`: пропускаем пробелы и знаки, потом буквы.
+    fn prev_word(&self, position: usize) -> usize {
+        let graphemes: Vec<(usize, &str)> = self.text[..position].grapheme_indices(true).collect();
+        let mut index = graphemes.len();
+        while index > 0 && !is_word(graphemes[index - 1].1) {
+            index -= 1;
+        }
+        while index > 0 && is_word(graphemes[index - 1].1) {
+            index -= 1;
+        }
+        graphemes.get(index).map_or(position, |(offset, _)| *offset)
+    }
+
+    /// Конец слова справа от `position`.
+    fn next_word(&self, position: usize) -> usize {
+        let mut graphemes = self.text[position..].grapheme_indices(true).peekable();
+        while graphemes.next_if(|(_, grapheme)| !is_word(grapheme)).is_some() {}
+        while graphemes.next_if(|(_, grapheme)| is_word(grapheme)).is_some() {}
+        graphemes.peek().map_or(self.text.len(), |(offset, _)| position + offset)
+    }
+}
+
+/// Первые `max_length` графем строки.
+fn truncate(text: &str, max_length: Option<usize>) -> &str {
+    match max_length.and_then(|max| text.grapheme_indices(true).nth(max)) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+fn is_word(grapheme: &str) -> bool {
+    grapheme.chars().next().is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Семья из четырех человек: одна графема из семи кодовых точек, склеенных ZWJ.
+    const FAMILY: &str = "👨\u{200d}👩\u{200d}👧\u{200d}👦";
+    /// Буква с комбинируемым ударением — тоже одна графема.
+    const ACCENTED: &str = "е\u{301}ж";
+
+    fn filled(text: &str) -> TextEditor {
+        let mut editor = TextEditor::new();
+        editor.insert(text);
+        editor
+    }
+
+    #[test]
+    fn caret_moves_over_whole_graphemes() {
+        let mut editor = filled(&format!("ё{}й", FAMILY));
+        editor.move_left(false, false);
+        assert_eq!(editor.caret(), editor.text().len() - "й".len());
+        editor.move_left(false, false);
+        assert_eq!(editor.caret(), "ё".len(), "the ZWJ sequence is one step");
+        editor.move_left(false, false);
+        assert_eq!(editor.caret(), 0);
+        editor.move_left(false, false);
+        assert_eq!(editor.caret(), 0);
+
+        editor.move_right(false, false);
+        editor.move_right(false, false);
+        assert_eq!(editor.caret(), "ё".len() + FAMILY.len());
+    }
+
+    #[test]
+    fn caret_snaps_to_grapheme_boundaries() {
+        let mut editor = filled(&format!("a{}", FAMILY));
+        // Позиция внутри последовательности округляется к ее началу
+        editor.set_caret(1 + "👨".len(), false);
+        assert_eq!(editor.caret(), 1);
+        let mut accented = filled(ACCENTED);
+        accented.set_caret("е".len(), false);
+        assert_eq!(accented.caret(), 0);
+    }
+
+    #[test]
+    fn backspace_removes_one_grapheme() {
+        let mut editor = filled(&format!("привет{}", FAMILY));
+        editor.backspace(false);
+        assert_eq!(editor.text(), "привет");
+        editor.backspace(false);
+        assert_eq!(editor.text(), "приве");
+
+        let mut accented = filled(ACCENTED);
+        accented.home(false);
+        accented.delete(false);
+        assert_eq!(accented.text(), "ж", "the accent goes away with its letter");
+    }
+
+    #[test]
+    fn word_jumps_skip_spaces_and_punctuation() {
+        let mut editor = filled("привет, мир  foo_bar");
+        editor.move_left(false, true);
+        assert_eq!(&editor.text()[editor.caret()..], "foo_bar");
+        editor.move_left(false, true);
+        assert_eq!(&editor.text()[editor.caret()..], "мир  foo_bar");
+        editor.move_left(false, true);
+        assert_eq!(editor.caret(), 0);
+
+        editor.move_right(false, true);
+        assert_eq!(&editor.text()[..editor.caret()], "привет");
+        editor.move_right(false, true);
+        assert_eq!(&editor.text()[..editor.caret()], "привет, мир");
+        editor.move_right(false, true);
+        assert_eq!(editor.caret(), editor.text().len());
+    }
+
+    #[test]
+    fn word_deletion() {
+        let mut editor = filled("hello brave world");
+        editor.backspace(true);
+        assert_eq!(editor.text(), "hello brave ");
+        editor.home(false);
+        editor.delete(true);
+        assert_eq!(editor.text(), " brave ");
+    }
+
+    #[test]
+    fn typing_replaces_selection() {
+        let mut editor = filled("hello world");
+        editor.move_left(true, true);
+        assert_eq!(editor.selection(), Some(6..11));
+        editor.insert("мир");
+        assert_eq!(editor.text(), "hello мир");
+        assert_eq!(editor.selection(), None);
+        assert_eq!(editor.caret(), editor.text().len());
+
+        editor.select_all();
+        editor.backspace(false);
+        assert_eq!(editor.text(), "");
+    }
+
+    #[test]
+    fn arrow_without_shift_collapses_selection() {
+        let mut editor = filled("abcdef");
+        editor.set_caret(2, false);
+        editor.set_caret(4, true);
+        editor.move_left(false, false);
+        assert_eq!((editor.caret(), editor.selection()), (2, None));
+    }
+
+    #[test]
+    fn consecutive_edits_undo_as_one_step() {
+        let mut editor = TextEditor::new();
+        for text in ["h", "e", "l", "l", "o"] {
+            editor.insert(text);
+        }
+        editor.backspace(false);
+        editor.backspace(false);
+        assert_eq!(editor.text(), "hel");
+
+        editor.undo();
+        assert_eq!(editor.text(), "hello", "both backspaces are one step");
+        editor.undo();
+        assert_eq!(editor.text(), "", "the typing is one step");
+        editor.redo();
+        assert_eq!(editor.text(), "hello");
+    }
+
+    #[test]
+    fn moving_the_caret_starts_a_new_undo_step() {
+        let mut editor = filled("ab");
+        editor.home(false);
+        editor.insert("x");
+        editor.undo();
+        assert_eq!(editor.text(), "ab");
+        editor.undo();
+        assert_eq!(editor.text(), "");
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut editor = filled("one");
+        editor.undo();
+        assert_eq!(editor.text(), "");
+        editor.insert("two");
+        editor.redo();
+        assert_eq!(editor.text(), "two");
+    }
+
+    #[test]
+    fn max_length_counts_graphemes() {
+        let mut editor = TextEditor::new();
+        editor.set_max_length(Some(3));
+        editor.insert(&format!("ё{}жзи", FAMILY));
+        assert_eq!(editor.text(), format!("ё{}ж", FAMILY));
+        editor.insert("к");
+        assert_eq!(editor.text(), format!("ё{}ж", FAMILY), "a full field ignores input");
+
+        editor.set_text("абвгд");
+        assert_eq!(editor.text(), "абв");
+    }
+
+    #[test]
+    fn max_length_leaves_room_for_replaced_selection() {
+        let mut editor = TextEditor::new();
+        editor.set_max_length(Some(5));
+        editor.insert("abcde");
+        // Выделены две графемы: их место занимают первые две вставляемые
+        editor.set_caret(1, false);
+        editor.set_caret(3, true);
+        editor.insert("ЖЗИК");
+        assert_eq!(editor.text(), "aЖЗde");
+        assert_eq!(editor.caret(), "aЖЗ".len());
+    }
+}
@@ -0,0 +1,413 @@
+// src/ui/text_input.rs
+
+use crate::backend::AssetSpec;
+use crate::ui::canvas::Canvas;
+use crate::ui::skin::NineSliceSkin;
+use crate::ui::text_edit::TextEditor;
+use crate::ui::widget::{DrawContext, EditKey, EventContext, PointerState, Rect, UiEvent, Widget};
+use crate::ui::widgets::{draw_nine_slice, VisualState, TRANSITION_DURATION};
+use rusttype::{point, Font, PositionedGlyph, Scale};
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::time::Duration;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Период мигания курсора.
+const CURSOR_BLINK: Duration = Duration::from_millis(500);
+/// Отступ текста от левого и правого края поля.
+const TEXT_PADDING: i32 = 10;
+/// Чем заменяется каждая графема в режиме пароля.
+const MASK_CHAR: char = '*';
+
+const TEXT_COLOR: [u8; 4] = [220, 220, 220, 255];
+const PLACEHOLDER_COLOR: [u8; 4] = [120, 120, 135, 255];
+const SELECTION_COLOR: [u8; 4] = [50, 90, 160, 255];
+
+/// Однострочное поле ввода с курсором, выделением и отменой.
+pub struct TextInput {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub is_focused: bool,
+    editor: TextEditor,
+    font: Arc<Font<'static>>,
+    /// Когда курсор последний раз мигнул, по часам приложения.
+    cursor_timer: Duration,
+    cursor_visible: bool,
+    /// Мышь зажата внутри поля: ее движение тянет выделение.
+    dragging: bool,
+    /// Nine-slice фон поля: Idle без фокуса, Hovered с фокусом.
+    skin: Option<Arc<NineSliceSkin>>,
+    /// Когда пришла картинка скина: от этого момента фон проявляется поверх запасного.
+    skin_arrived: Option<Duration>,
+    /// Текст, который сейчас набирается в IME, и позиция курсора в нем (в байтах).
+    preedit: String,
+    preedit_cursor: Option<usize>,
+    /// Подсказка, которая видна, пока поле пустое.
+    placeholder: Option<String>,
+    /// Показывать вместо символов маску.
+    password: bool,
+    /// На сколько пикселей текст сдвинут влево, чтобы курсор оставался в поле.
+    scroll: i32,
+    /// Поле состояния приложения, в которое пишется текст.
+    binding: Option<String>,
+}
+
+impl TextInput {
+    pub fn new(x: i32, y: i32, width: u32, height: u32, font: Arc<Font<'static>>) -> Self {
+        Self {
+            x, y, width, height,
+            is_focused: false,
+            editor: TextEditor::new(),
+            font,
+            cursor_timer: Duration::ZERO,
+            cursor_visible: false,
+            dragging: false,
+            skin: None,
+            skin_arrived: None,
+            preedit: String::new(),
+            preedit_cursor: None,
+            placeholder: None,
+            password: false,
+            scroll: 0,
+            binding: None,
+        }
+    }
+
+    pub fn with_skin(mut self, skin: Arc<NineSliceSkin>) -> Self {
+        self.skin = Some(skin);
+        self
+    }
+
+    pub fn with_placeholder(mut self, placeholder: &str) -> Self {
+        self.placeholder = Some(placeholder.to_string());
+        self
+    }
+
+    /// Режим пароля: текст скрыт маской.
+    pub fn with_password(mut self, password: bool) -> Self {
+        self.password = password;
+        self
+    }
+
+    /// Привязывает текст поля к полю состояния приложения `binding`.
+    pub fn with_binding(mut self, binding: &str) -> Self {
+        self.binding = Some(binding.to_string());
+        self
+    }
+
+    /// Наибольшая длина текста в графемах.
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.editor.set_max_length(Some(max_length));
+        self
+    }
+
+    pub fn text(&self) -> &str {
+        self.editor.text()
+    }
+
+    /// Заменяет текст поля; курсор встает в конец.
+    pub fn set_text(&mut self, text: &str) {
+        self.editor.set_text(text);
+        self.scroll_to_caret();
+    }
+
+    pub fn clear(&mut self) {
+        self.set_text("");
+    }
+
+    pub fn is_over(&self, mouse_pos: (i32, i32)) -> bool {
+        self.bounds().contains(mouse_pos)
+    }
+
+    fn handle_key(&mut self, key: EditKey, shift: bool, word: bool) {
+        // Границы слов выдали бы устройство пароля, поэтому в нем сочетания по словам
+        // работают как обычные клавиши
+        let word = word && !self.password;
+        match key {
+            EditKey::Backspace => self.editor.backspace(word),
+            EditKey::Delete => self.editor.delete(word),
+            EditKey::Left => self.editor.move_left(shift, word),
+            EditKey::Right => self.editor.move_right(shift, word),
+            EditKey::Home => self.editor.home(shift),
+            EditKey::End => self.editor.end(shift),
+            EditKey::SelectAll => self.editor.select_all(),
+            EditKey::Undo => self.editor.undo(),
+            EditKey::Redo => self.editor.redo(),
+        }
+    }
+
+    /// Курсор только что сдвинулся: показываем его сразу, не дожидаясь мигания.
+    fn show_cursor(&mut self, now: Duration) {
+        self.cursor_visible = true;
+        self.cursor_timer = now;
+    }
+
+    /// Базовая линия текста, вертикально отцентрованного в поле.
+    fn baseline(&self, scale: Scale) -> f32 {
+        let v_metrics = self.font.v_metrics(scale);
+        self.y as f32 + (self.height as f32 - (v_metrics.ascent - v_metrics.descent)) / 2.0 + v_metrics.ascent
+    }
+
+    fn scale() -> Scale {
+        Scale { x: 24.0, y: 24.0 }
+    }
+
+    /// Внутренняя область поля, в которой виден текст.
+    fn text_area(&self) -> Rect {
+        let width = self.width.saturating_sub(2 * TEXT_PADDING as u32);
+        Rect::new(self.x + TEXT_PADDING, self.y, width, self.height)
+    }
+
+    /// Текст так, как его видно в поле: в пароле каждая графема заменена маской.
+    fn visible_text(&self) -> Cow<'_, str> {
+        if self.password {
+            Cow::Owned(MASK_CHAR.to_string().repeat(self.editor.text().graphemes(true).count()))
+        } else {
+            Cow::Borrowed(self.editor.text())
+        }
+    }
+
+    /// Незавершенный ввод IME так, как его видно в поле: в пароле он тоже замаскирован.
+    fn visible_preedit(&self) -> Cow<'_, str> {
+        if self.password {
+            Cow::Owned(MASK_CHAR.to_string().repeat(self.preedit.graphemes(true).count()))
+        } else {
+            Cow::Borrowed(&self.preedit)
+        }
+    }
+
+    /// Видимый текст с незавершенным вводом IME в позиции курсора.
+    fn shown_text(&self) -> String {
+        let visible = self.visible_text();
+        let caret = self.visible_offset(self.editor.caret());
+        format!("{}{}{}", &visible[..caret], self.visible_preedit(), &visible[caret..])
+    }
+
+    /// Смещение в тексте редактора -> смещение в видимом тексте.
+    fn visible_offset(&self, offset: usize) -> usize {
+        if self.password {
+            self.editor.text()[..offset].graphemes(true).count() * MASK_CHAR.len_utf8()
+        } else {
+            offset
+        }
+    }
+
+    /// Смещение в тексте редактора -> смещение в `shown_text`.
+    fn shown_offset(&self, offset: usize) -> usize {
+        let visible = self.visible_offset(offset);
+        if offset <= self.editor.caret() { visible } else { visible + self.visible_preedit().len() }
+    }
+
+    /// Где в `shown_text` стоит курсор: внутри незавершенного ввода, если он есть.
+    fn shown_caret(&self) -> usize {
+        let cursor = match self.preedit_cursor {
+            Some(cursor) if !self.preedit.is_empty() => cursor.min(self.preedit.len()),
+            _ => self.preedit.len(),
+        };
+        let in_preedit = if self.password {
+            // IME ставит курсор на границу графемы, а каждая графема в маске — один символ
+            self.preedit.get(..cursor).unwrap_or(&self.preedit).graphemes(true).count() * MASK_CHAR.len_utf8()
+        } else {
+            cursor
+        };
+        self.visible_offset(self.editor.caret()) + in_preedit
+    }
+
+    /// Где начинается строка с учетом прокрутки.
+    fn text_origin(&self) -> f32 {
+        (self.text_area().x - self.scroll) as f32
+    }
+
+    /// Раскладывает строку в поле с учетом прокрутки.
+    fn layout_text(&self, text: &str, scale: Scale) -> Vec<PositionedGlyph<'static>> {
+        let origin = point(self.text_origin(), self.baseline(scale));
+        self.font.layout(text, scale, origin).collect()
+    }
+
+    /// Ближайшая к точке `x` граница графемы.
+    fn offset_at(&self, x: i32) -> usize {
+        let shown = self.shown_text();
+        let glyphs = self.layout_text(&shown, Self::scale());
+        self.editor
+            .boundaries()
+            .into_iter()
+            .min_by_key(|&offset| (x_at(&glyphs, &shown, self.shown_offset(offset), self.text_origin()) - x as f32).abs() as i32)
+            .unwrap_or(0)
+    }
+
+    /// Прокручивает текст так, чтобы курсор был виден, а справа от текста
+    /// не оставалось пустоты, пока текст шире поля.
+    fn scroll_to_caret(&mut self) {
+        let shown = self.shown_text();
+        let glyphs = self.layout_text(&shown, Self::scale());
+        let origin = self.text_origin();
+        let caret_x = (x_at(&glyphs, &shown, self.shown_caret(), origin) - origin) as i32;
+        let text_width = (x_at(&glyphs, &shown, shown.len(), origin) - origin) as i32;
+        // Последний пиксель области оставляем под курсор
+        let visible = self.text_area().width as i32 - 1;
+
+        self.scroll = self.scroll.min((text_width - visible).max(0));
+        if caret_x - self.scroll > visible {
+            self.scroll = caret_x - visible;
+        }
+        if caret_x < self.scroll {
+            self.scroll = caret_x;
+        }
+    }
+}
+
+impl Widget for TextInput {
+    fn handle_event(&mut self, event: &UiEvent, ctx: &mut EventContext) {
+        match event {
+            // Нажатие в поле дает ему фокус и ставит курсор, нажатие мимо — забирает фокус
+            UiEvent::MouseDown => {
+                self.is_focused = self.is_over(ctx.pointer.pos);
+                if self.is_focused {
+                    let offset = self.offset_at(ctx.pointer.pos.0);
+                    self.editor.set_caret(offset, false);
+                    self.dragging = true;
+                    self.show_cursor(ctx.now);
+                } else {
+                    self.preedit.clear();
+                }
+            }
+            UiEvent::MouseMoved { x, .. } if self.dragging => {
+                let offset = self.offset_at(*x);
+                self.editor.set_caret(offset, true);
+                self.show_cursor(ctx.now);
+            }
+            UiEvent::MouseUp => self.dragging = false,
+            _ if !self.is_focused => return,
+            UiEvent::Text(text) => {
+                // Подтвержденный текст заменяет то, что набиралось в IME
+                self.preedit.clear();
+                self.editor.insert(text);
+                self.show_cursor(ctx.now);
+            }
+            UiEvent::Preedit { text, cursor } => {
+                self.preedit = text.clone();
+                self.preedit_cursor = *cursor;
+            }
+            UiEvent::Key { key, modifiers } => {
+                self.handle_key(*key, modifiers.shift, modifiers.word());
+                self.show_cursor(ctx.now);
+            }
+            _ => return,
+        }
+        self.scroll_to_caret();
+    }
+
+    fn update(&mut self, _pointer: PointerState, now: Duration) {
+        if self.is_focused && now.saturating_sub(self.cursor_timer) > CURSOR_BLINK {
+            self.cursor_visible = !self.cursor_visible;
+            self.cursor_timer = now;
+        }
+    }
+
+    fn settle(&mut self) {
+        self.skin_arrived = None;
+    }
+
+    fn bounds(&self) -> Rect {
+        Rect::new(self.x, self.y, self.width, self.height)
+    }
+
+    fn layout(&mut self, bounds: Rect) {
+        (self.x, self.y, self.width, self.height) = (bounds.x, bounds.y, bounds.width, bounds.height);
+        self.scroll_to_caret();
+    }
+
+    /// Фоны, которые нужны полю ввода.
+    fn asset_specs(&self) -> Vec<AssetSpec> {
+        self.skin.as_ref().map_or_else(Vec::new, |skin| skin.asset_specs())
+    }
+
+    fn binding(&self) -> Option<&str> {
+        self.binding.as_deref()
+    }
+
+    fn value(&self) -> Option<String> {
+        Some(self.text().to_string())
+    }
+
+    fn set_value(&mut self, value: &str) {
+        self.set_text(value);
+    }
+
+    /// Фон скина проявляется плавно, когда приходит его картинка.
+    fn on_asset_arrived(&mut self, key: &str, now: Duration) {
+        let is_own = self.skin.as_ref().is_some_and(|skin| {
+            [VisualState::Idle, VisualState::Hovered].iter().any(|state| skin.asset_key(*state) == key)
+        });
+        if is_own {
+            self.skin_arrived = Some(now);
+        }
+    }
+
+    fn draw(&self, canvas: &mut Canvas, ctx: &DrawContext) {
+        let cache = ctx.render_cache;
+
+        // 1. Фон: скин, проявляющийся поверх запасного прямоугольника
+        let state = if self.is_focused { VisualState::Hovered } else { VisualState::Idle };
+        let skin_image = self.skin.as_ref().and_then(|skin| cache.get(&skin.asset_key(state)).map(|image| (skin, image)));
+        let alpha = self.skin_arrived.map_or(1.0, |arrived| (ctx.now.saturating_sub(arrived).as_secs_f32() / TRANSITION_DURATION.as_secs_f32()).min(1.0));
+        if skin_image.is_none() || alpha < 1.0 {
+            let bg_color = if self.is_focused { [50, 50, 60, 255] } else { [30, 30, 40, 255] };
+            canvas.fill(self.bounds(), bg_color);
+        }
+        if let Some((skin, image)) = skin_image {
+            draw_nine_slice(canvas, image, skin, self.bounds(), alpha);
+        }
+
+        // 2. Текст, обрезанный по внутренней области поля. Незавершенный ввод IME
+        // вставляется в позицию курсора и подчеркивается
+        canvas.push_clip(self.text_area());
+        let scale = Self::scale();
+        let v_metrics = self.font.v_metrics(scale);
+        let text_top = (self.baseline(scale) - v_metrics.ascent) as i32;
+        let line_height = (v_metrics.ascent - v_metrics.descent) as u32;
+
+        let shown = self.shown_text();
+        let glyphs = self.layout_text(&shown, scale);
+        let x_of = |offset: usize| x_at(&glyphs, &shown, offset, self.text_origin()) as i32;
+
+        if let Some(selection) = self.editor.selection() {
+            let (start, end) = (x_of(self.shown_offset(selection.start)), x_of(self.shown_offset(selection.end)));
+            canvas.fill(Rect::new(start, text_top, (end - start).max(0) as u32, line_height), SELECTION_COLOR);
+        }
+        if !self.preedit.is_empty() {
+            let caret = self.visible_offset(self.editor.caret());
+            let (start, end) = (x_of(caret), x_of(caret + self.preedit.len()));
+            let underline_y = self.baseline(scale) as i32 + 2;
+            canvas.fill(Rect::new(start, underline_y, (end - start).max(0) as u32, 1), TEXT_COLOR);
+        }
+        if shown.is_empty() {
+            if let Some(placeholder) = &self.placeholder {
+                canvas.glyphs(self.layout_text(placeholder, scale), PLACEHOLDER_COLOR);
+            }
+        } else {
+            canvas.glyphs(glyphs.iter().cloned(), TEXT_COLOR);
+        }
+
+        // 3. Курсор
+        if self.is_focused && self.cursor_visible {
+            canvas.fill(Rect::new(x_of(self.shown_caret()), text_top, 1, line_height), TEXT_COLOR);
+        }
+        canvas.pop_clip();
+    }
+}
+
+/// x-координата позиции `offset` (в байтах) строки `text`, разложенной в `glyphs`.
+/// `font.layout` дает по одному глифу на символ, поэтому глифы идут в порядке `char_indices`.
+/// У пустой строки глифов нет, и позиция — это начало строки `origin`.
+fn x_at(glyphs: &[PositionedGlyph], text: &str, offset: usize, origin: f32) -> f32 {
+    let index = text.char_indices().take_while(|(byte, _)| *byte < offset).count();
+    match glyphs.get(index) {
+        Some(glyph) => glyph.position().x,
+        None => glyphs
+            .last()
+            .map_or(origin, |glyph| glyph.position().x + glyph.unpositioned().h_metrics().advance_width),
+    }
+}
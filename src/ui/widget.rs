@@ -0,0 +1,266 @@
+// src/ui/widget.rs
+
+use crate::backend::AssetSpec;
+use crate::ui::canvas::Canvas;
+use image::DynamicImage;
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use winit::event::{ElementState, Ime, KeyEvent, MouseButton, WindowEvent};
+use winit::keyboard::{Key, KeyCode, ModifiersState, NamedKey, PhysicalKey};
+
+/// Прямоугольник на экране в пикселях кадра.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    pub fn contains(&self, (px, py): (i32, i32)) -> bool {
+        px >= self.x && px <= self.x + self.width as i32 && py >= self.y && py <= self.y + self.height as i32
+    }
+}
+
+/// Событие ввода в виде, не зависящем от оконной системы.
+#[derive(Clone, Debug, PartialEq)]
+pub enum UiEvent {
+    /// Курсор переместился в точку кадра `(x, y)`.
+    MouseMoved { x: i32, y: i32 },
+    /// Левая кнопка мыши нажата.
+    MouseDown,
+    /// Левая кнопка мыши отпущена.
+    MouseUp,
+    /// Готовый текст для вставки: напечатанный символ или результат IME.
+    Text(String),
+    /// Незавершенный ввод IME. `cursor` — позиция курсора внутри `text` в байтах;
+    /// пустой `text` означает, что набор закончен или отменен.
+    Preedit { text: String, cursor: Option<usize> },
+    /// Клавиша редактирования текста.
+    Key { key: EditKey, modifiers: Modifiers },
+}
+
+/// Клавиши и сочетания, которые понимают поля ввода.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditKey {
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+    SelectAll,
+    Undo,
+    Redo,
+}
+
+/// Зажатые модификаторы.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+    pub super_key: bool,
+}
+
+impl Modifiers {
+    /// Перемещение и удаление по словам: Ctrl, а на macOS — Alt.
+    pub fn word(&self) -> bool {
+        self.control || self.alt
+    }
+}
+
+/// Переводит события окна в `UiEvent`. Помнит нажатые модификаторы, поэтому
+/// должен видеть все события окна, а не только нужные UI.
+#[derive(Debug, Default)]
+pub struct InputTranslator {
+    modifiers: ModifiersState,
+    /// Идет набор в IME: клавиши в это время принадлежат ему.
+    composing: bool,
+}
+
+impl InputTranslator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Перемещение курсора сюда не входит: его координаты надо сначала перевести в пиксели кадра.
+    pub fn translate(&mut self, event: &WindowEvent) -> Option<UiEvent> {
+        match event {
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+                None
+            }
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => Some(match state {
+                ElementState::Pressed => UiEvent::MouseDown,
+                ElementState::Released => UiEvent::MouseUp,
+            }),
+            WindowEvent::KeyboardInput { event, .. } if event.state == ElementState::Pressed && !self.composing => self.translate_key(event),
+            WindowEvent::Ime(Ime::Preedit(text, cursor)) => {
+                self.composing = !text.is_empty();
+                Some(UiEvent::Preedit { text: text.clone(), cursor: cursor.map(|(_, end)| end) })
+            }
+            WindowEvent::Ime(Ime::Commit(text)) => {
+                self.composing = false;
+                printable(text).map(UiEvent::Text)
+            }
+            WindowEvent::Ime(Ime::Disabled) => {
+                self.composing = false;
+                Some(UiEvent::Preedit { text: String::new(), cursor: None })
+            }
+            _ => None,
+        }
+    }
+
+    fn translate_key(&self, event: &KeyEvent) -> Option<UiEvent> {
+        let modifiers = Modifiers {
+            shift: self.modifiers.shift_key(),
+            control: self.modifiers.control_key(),
+            alt: self.modifiers.alt_key(),
+            super_key: self.modifiers.super_key(),
+        };
+        // Ctrl+символ и Super+символ — это сочетания клавиш, а не ввод текста.
+        // Ctrl вместе с Alt пропускаем: так Windows сообщает об AltGr.
+        let shortcut = (modifiers.control && !modifiers.alt) || modifiers.super_key;
+
+        let key = match event.logical_key {
+            Key::Named(NamedKey::Backspace) => Some(EditKey::Backspace),
+            Key::Named(NamedKey::Delete) => Some(EditKey::Delete),
+            Key::Named(NamedKey::ArrowLeft) => Some(EditKey::Left),
+            Key::Named(NamedKey::ArrowRight) => Some(EditKey::Right),
+            Key::Named(NamedKey::Home) => Some(EditKey::Home),
+            Key::Named(NamedKey::End) => Some(EditKey::End),
+            // Сочетания берем по физической клавише, чтобы они работали и в русской раскладке
+            _ if shortcut => match event.physical_key {
+                PhysicalKey::Code(KeyCode::KeyA) => Some(EditKey::SelectAll),
+                PhysicalKey::Code(KeyCode::KeyZ) if modifiers.shift => Some(EditKey::Redo),
+                PhysicalKey::Code(KeyCode::KeyZ) => Some(EditKey::Undo),
+                PhysicalKey::Code(KeyCode::KeyY) => Some(EditKey::Redo),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(key) = key {
+            return Some(UiEvent::Key { key, modifiers });
+        }
+        if shortcut {
+            return None;
+        }
+        event.text.as_deref().and_then(printable).map(UiEvent::Text)
+    }
+}
+
+/// Текст без управляющих символов (`\r`, `\t`, DEL и т. п.); `None`, если ничего не осталось.
+fn printable(text: &str) -> Option<String> {
+    let text: String = text.chars().filter(|c| !c.is_control()).collect();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Состояние мыши, которое видят все виджеты.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PointerState {
+    pub pos: (i32, i32),
+    pub pressed: bool,
+}
+
+/// Контекст обработки события: состояние мыши и то, что виджеты сообщают приложению.
+#[derive(Debug, Default)]
+pub struct EventContext {
+    pub pointer: PointerState,
+    /// Время кадра по часам приложения.
+    pub now: Duration,
+    /// Имена действий, которые запросили виджеты, по порядку.
+    pub actions: Vec<String>,
+}
+
+/// Все, что нужно виджету для отрисовки.
+pub struct DrawContext<'a> {
+    /// Время кадра по часам приложения: от него считается прогресс анимаций.
+    pub now: Duration,
+    /// Готовые фоны по логическим ключам ассетов.
+    pub render_cache: &'a HashMap<String, Arc<DynamicImage>>,
+}
+
+/// Общий интерфейс элементов UI. `AppUi` обходит дерево виджетов сам, поэтому
+/// контейнеру достаточно отдать детей через `children`/`children_mut`.
+pub trait Widget: Any {
+    /// Реагирует на событие ввода.
+    fn handle_event(&mut self, event: &UiEvent, ctx: &mut EventContext);
+
+    /// Обновление раз в кадр: анимации, мигание курсора. `now` — время кадра по часам приложения.
+    fn update(&mut self, _pointer: PointerState, _now: Duration) {}
+
+    /// Мгновенно доводит анимации до конца: так снимок экрана не зависит от того,
+    /// сколько времени прошло.
+    fn settle(&mut self) {}
+
+    /// Где виджет находится сейчас.
+    fn bounds(&self) -> Rect;
+
+    /// Задает виджету место на экране.
+    fn layout(&mut self, bounds: Rect);
+
+    fn draw(&self, canvas: &mut Canvas, ctx: &DrawContext);
+
+    /// Фоны, которые виджет просит сгенерировать.
+    fn asset_specs(&self) -> Vec<AssetSpec> {
+        Vec::new()
+    }
+
+    /// В кэш пришла картинка `key` уже после запуска UI; `now` — время кадра.
+    fn on_asset_arrived(&mut self, _key: &str, _now: Duration) {}
+
+    /// Поле состояния приложения, к которому привязан виджет (`bind` в описании экрана).
+    fn binding(&self) -> Option<&str> {
+        None
+    }
+
+    /// Значение, которое виджет отдает в привязанное поле; `None` — виджет его только показывает.
+    fn value(&self) -> Option<String> {
+        None
+    }
+
+    /// Показывает новое значение привязанного поля.
+    fn set_value(&mut self, _value: &str) {}
+
+    fn children(&self) -> &[Box<dyn Widget>] {
+        &[]
+    }
+
+    fn children_mut(&mut self) -> &mut [Box<dyn Widget>] {
+        &mut []
+    }
+}
+
+/// Обходит дерево виджетов: сначала родитель, потом его дети.
+pub fn visit(widgets: &[Box<dyn Widget>], visitor: &mut dyn FnMut(&dyn Widget)) {
+    for widget in widgets {
+        visitor(widget.as_ref());
+        visit(widget.children(), visitor);
+    }
+}
+
+pub fn visit_mut(widgets: &mut [Box<dyn Widget>], visitor: &mut dyn FnMut(&mut dyn Widget)) {
+    for widget in widgets {
+        visitor(widget.as_mut());
+        visit_mut(widget.children_mut(), visitor);
+    }
+}
+
+/// Рисует дерево виджетов. Виджет и его дети обрезаются по границам виджета,
+/// поэтому ни один из них не рисует поверх соседей.
+pub fn draw_tree(widgets: &[Box<dyn Widget>], canvas: &mut Canvas, ctx: &DrawContext) {
+    for widget in widgets {
+        canvas.push_clip(widget.bounds());
+        widget.draw(canvas, ctx);
+        draw_tree(widget.children(), canvas, ctx);
+        canvas.pop_clip();
+    }
+}
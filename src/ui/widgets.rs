@@ -1,12 +1,16 @@
 // src/ui/widgets.rs
 
+use crate::backend::AssetSpec;
+use crate::ui::canvas::Canvas;
+use crate::ui::skin::{NineSliceSkin, SliceMode};
+use crate::ui::widget::{DrawContext, EventContext, PointerState, Rect, UiEvent, Widget};
 use rusttype::{point, Font, Scale};
-use std::time::{Duration, Instant};
+use std::time::Duration;
 use std::collections::HashMap;
-use image::{DynamicImage, GenericImageView, Rgba, Pixel}; // ИСПРАВЛЕНИЕ: Импортируем трейт Pixel
+use image::{DynamicImage, GenericImageView};
 use std::sync::Arc;
 
-const TRANSITION_DURATION: Duration = Duration::from_millis(200);
+pub(crate) const TRANSITION_DURATION: Duration = Duration::from_millis(200);
 
 /// Визуальные состояния, которые может сгенерировать AI.
 #[derive(PartialEq, Clone, Copy, Debug, Hash, Eq)]
@@ -19,9 +23,18 @@ pub enum VisualState {
 /// Структура, описывающая переход из одного состояния в другое.
 #[derive(Debug)]
 struct Transition {
-    from: VisualState,
+    /// `None` — запасной фон: картинки еще не было, и она только что пришла.
+    from: Option<VisualState>,
     to: VisualState,
-    start: Instant,
+    /// Время начала по часам приложения.
+    start: Duration,
+}
+
+impl Transition {
+    /// Пройденная доля перехода к моменту `now`, от 0 до 1.
+    fn progress(&self, now: Duration) -> f32 {
+        (now.saturating_sub(self.start).as_secs_f32() / TRANSITION_DURATION.as_secs_f32()).min(1.0)
+    }
 }
 
 /// Текущее состояние кнопки: либо стабильное, либо в процессе анимации.
@@ -30,6 +43,9 @@ enum ButtonState {
     Animating(Transition),
 }
 
+/// Промпт фона кнопки по умолчанию.
+pub const DEFAULT_BUTTON_PROMPT: &str = "a crisp UI button background, no text, photorealistic, octane render, trending on artstation, dark sci-fi style, neon blue highlights";
+
 /// Наш новый, умный виджет кнопки.
 pub struct Button {
     pub id: usize,
@@ -40,6 +56,14 @@ pub struct Button {
     state: ButtonState,
     text: String,
     font: Arc<Font<'static>>,
+    /// Собственный сид кнопки; `None` — сид выбирает `SeedPolicy`.
+    seed: Option<i64>,
+    /// Общий масштабируемый скин; `None` — у кнопки свои картинки ровно ее размера.
+    skin: Option<Arc<NineSliceSkin>>,
+    /// Описание фона для AI; к нему добавляется суффикс состояния.
+    prompt: String,
+    /// Имя действия, которое выполняет кнопка.
+    action: Option<String>,
 }
 
 impl Button {
@@ -49,18 +73,48 @@ impl Button {
             state: ButtonState::Stable(VisualState::Idle),
             text,
             font,
+            seed: None,
+            skin: None,
+            prompt: DEFAULT_BUTTON_PROMPT.to_string(),
+            action: None,
         }
     }
 
-    /// Обновляет состояние кнопки, управляя анимациями. Возвращает `true` при клике.
-    pub fn update(&mut self, mouse_pos: (i32, i32), mouse_pressed: bool, mouse_clicked: bool) -> bool {
-        let is_over = mouse_pos.0 >= self.x
-            && mouse_pos.0 <= self.x + self.width as i32
-            && mouse_pos.1 >= self.y
-            && mouse_pos.1 <= self.y + self.height as i32;
+    /// Задает свое описание фона вместо стандартного.
+    pub fn with_prompt(mut self, prompt: &str) -> Self {
+        self.prompt = prompt.to_string();
+        self
+    }
+
+    pub fn with_action(mut self, action: &str) -> Self {
+        self.action = Some(action.to_string());
+        self
+    }
+
+    pub fn action(&self) -> Option<&str> {
+        self.action.as_deref()
+    }
+
+    /// Рисует кнопку общим nine-slice скином вместо собственных картинок.
+    pub fn with_skin(mut self, skin: Arc<NineSliceSkin>) -> Self {
+        self.skin = Some(skin);
+        self
+    }
 
-        let target_state = if is_over {
-            if mouse_pressed { VisualState::Pressed } else { VisualState::Hovered }
+    /// Закрепляет сид генерации за всеми состояниями кнопки.
+    pub fn with_seed(mut self, seed: i64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    fn is_over(&self, mouse_pos: (i32, i32)) -> bool {
+        self.bounds().contains(mouse_pos)
+    }
+
+    /// Обновляет состояние кнопки, управляя анимациями.
+    fn update_state(&mut self, pointer: PointerState, now: Duration) {
+        let target_state = if self.is_over(pointer.pos) {
+            if pointer.pressed { VisualState::Pressed } else { VisualState::Hovered }
         } else {
             VisualState::Idle
         };
@@ -68,18 +122,15 @@ impl Button {
         // ИСПРАВЛЕНИЕ: Рефакторинг для обхода borrow checker'a
         let mut transition_finished = false;
         let mut new_stable_state = VisualState::Idle; // временное значение
-        let mut current_visual_state = VisualState::Idle; // временное значение
 
-        match &self.state {
-            ButtonState::Stable(s) => {
-                current_visual_state = *s;
-            }
+        let current_visual_state = match &self.state {
+            ButtonState::Stable(s) => *s,
             ButtonState::Animating(t) => {
-                current_visual_state = t.to; // Для логики переходов считаем, что мы уже в целевом состоянии
-                if t.start.elapsed() >= TRANSITION_DURATION {
+                if t.progress(now) >= 1.0 {
                     transition_finished = true;
                     new_stable_state = t.to;
                 }
+                t.to // Для логики переходов считаем, что мы уже в целевом состоянии
             }
         };
         
@@ -93,139 +144,225 @@ impl Button {
                 ButtonState::Animating(ref t) => t.to,
             };
             self.state = ButtonState::Animating(Transition {
-                from: from_state,
+                from: Some(from_state),
                 to: target_state,
-                start: Instant::now(),
+                start: now,
             });
         }
-        
-        is_over && mouse_clicked
+    }
+
+    /// Запускает переход `from` -> `to` в момент `now`. Вместе с `ManualClock` позволяет
+    /// снять кадр анимации в точно заданный момент.
+    pub fn start_transition(&mut self, from: VisualState, to: VisualState, now: Duration) {
+        self.state = ButtonState::Animating(Transition { from: Some(from), to, start: now });
+    }
+
+    /// Логический ключ ассета кнопки в кэше, например `0-Hovered`.
+    pub fn asset_key(&self, state: VisualState) -> String {
+        match &self.skin {
+            Some(skin) => skin.asset_key(state),
+            None => format!("{}-{:?}", self.id, state),
+        }
     }
 
     /// Генерирует промпты для AI. ВАЖНО: теперь просим фон БЕЗ ТЕКСТА.
-    pub fn get_render_prompts(&self) -> HashMap<String, String> {
-        let mut prompts = HashMap::new();
-        let base_prompt = format!(
-            "a crisp UI button background, no text, photorealistic, octane render, trending on artstation, dark sci-fi style, neon blue highlights",
-        );
-        prompts.insert(format!("{}-{:?}", self.id, VisualState::Idle), format!("{}, normal state", base_prompt));
-        prompts.insert(format!("{}-{:?}", self.id, VisualState::Hovered), format!("{}, glowing, hovered state", base_prompt));
-        prompts.insert(format!("{}-{:?}", self.id, VisualState::Pressed), format!("{}, pressed down, indented", base_prompt));
-        prompts
+    pub fn get_render_prompts(&self) -> Vec<(VisualState, String)> {
+        let base_prompt = &self.prompt;
+        vec![
+            (VisualState::Idle, format!("{}, normal state", base_prompt)),
+            (VisualState::Hovered, format!("{}, glowing, hovered state", base_prompt)),
+            (VisualState::Pressed, format!("{}, pressed down, indented", base_prompt)),
+        ]
+    }
+
+    /// Описания всех фонов, которые нужны кнопке.
+    fn button_asset_specs(&self) -> Vec<AssetSpec> {
+        if let Some(skin) = &self.skin {
+            return skin.asset_specs();
+        }
+        self.get_render_prompts()
+            .into_iter()
+            .map(|(state, prompt)| {
+                let spec = AssetSpec::new(self.asset_key(state), prompt, self.width, self.height)
+                    .with_state(state)
+                    .with_widget(self.id.to_string())
+                    .with_seed(self.seed.unwrap_or(-1));
+                // Hovered и Pressed — это та же кнопка, поэтому их можно вывести из Idle
+                if state == VisualState::Idle {
+                    spec
+                } else {
+                    spec.derived_from(self.asset_key(VisualState::Idle))
+                }
+            })
+            .collect()
     }
 
     /// Отрисовывает кнопку, включая анимации и адаптивный текст.
-    pub fn draw(&self, frame: &mut [u8], screen_width: u32, cache: &HashMap<String, Arc<DynamicImage>>) {
+    fn draw_button(&self, canvas: &mut Canvas, cache: &HashMap<String, Arc<DynamicImage>>, now: Duration) {
         let (background, top_layer, progress) = match &self.state {
             ButtonState::Stable(state) => {
-                let key = format!("{}-{:?}", self.id, state);
-                (cache.get(&key), None, 0.0)
+                (cache.get(&self.asset_key(*state)), None, 0.0)
             }
             ButtonState::Animating(t) => {
-                let from_key = format!("{}-{:?}", self.id, t.from);
-                let to_key = format!("{}-{:?}", self.id, t.to);
-                let progress = (t.start.elapsed().as_secs_f32() / TRANSITION_DURATION.as_secs_f32()).min(1.0);
-                (cache.get(&from_key), cache.get(&to_key), progress)
+                let progress = t.progress(now);
+                let from = t.from.and_then(|state| cache.get(&self.asset_key(state)));
+                (from, cache.get(&self.asset_key(t.to)), progress)
             }
         };
 
         // 1. Отрисовка фона (и псевдо-анимации)
         if let Some(bg_image) = background {
-            draw_image(frame, screen_width, bg_image, self.x, self.y, 1.0 - progress);
-
-            if let Some(top_image) = top_layer {
-                draw_image(frame, screen_width, top_image, self.x, self.y, progress);
-            }
+            self.draw_background(canvas, bg_image, 1.0 - progress);
         } else {
-            // Запасной вариант, если картинка не найдена
-            draw_fallback_rect(frame, screen_width, self.x, self.y, self.width, self.height, [50, 50, 50, 255]);
+            // Запасной вариант, если картинка не найдена (или еще генерируется)
+            canvas.fill(self.bounds(), [50, 50, 50, 255]);
         }
-        
-        // 2. Отрисовка адаптивного текста
-        let text_image_source = top_layer.or(background).map(|i| Arc::clone(i));
-        if let Some(image_for_text) = text_image_source {
-            let text_color = calculate_contrast_color(&image_for_text);
-            draw_text(frame, screen_width, &self.font, &self.text, self.x, self.y, self.width, self.height, text_color);
+        if let Some(top_image) = top_layer {
+            self.draw_background(canvas, top_image, progress);
         }
+
+        // 2. Отрисовка адаптивного текста
+        let text_color = top_layer.or(background).map_or([240, 240, 240, 255], |image| calculate_contrast_color(image));
+        draw_text(canvas, &self.font, &self.text, self.bounds(), text_color);
     }
 }
 
-// --- Хелперы для отрисовки ---
-
-fn draw_image(frame: &mut [u8], screen_width: u32, image: &DynamicImage, x: i32, y: i32, alpha_multiplier: f32) {
-    for (px, py, pixel) in image.pixels() {
-        let screen_x = x + px as i32;
-        let screen_y = y + py as i32;
-        if screen_x >= 0 && screen_y >= 0 {
-            let index = ((screen_y as u32 * screen_width) + screen_x as u32) as usize * 4;
-            if index + 3 < frame.len() {
-                // Только рисуем пиксели с ненулевой альфой
-                if alpha_multiplier > 0.01 {
-                    let mut new_pixel = pixel;
-                    new_pixel.0[3] = (new_pixel.0[3] as f32 * alpha_multiplier) as u8;
-                    
-                    let old_pixel_slice = &mut frame[index..index + 4];
-                    let mut old_pixel = Rgba([old_pixel_slice[0], old_pixel_slice[1], old_pixel_slice[2], old_pixel_slice[3]]);
-                    
-                    // ИСПРАВЛЕНИЕ: теперь blend() в области видимости
-                    old_pixel.blend(&new_pixel);
-                    
-                    old_pixel_slice.copy_from_slice(&old_pixel.0);
-                }
+impl Widget for Button {
+    fn handle_event(&mut self, event: &UiEvent, ctx: &mut EventContext) {
+        // Клик — это отпускание кнопки мыши над кнопкой
+        if *event == UiEvent::MouseUp && self.is_over(ctx.pointer.pos) {
+            match &self.action {
+                Some(action) => ctx.actions.push(action.clone()),
+                None => println!("Button {} ('{}') has no action.", self.id, self.text),
             }
         }
     }
-}
 
+    fn update(&mut self, pointer: PointerState, now: Duration) {
+        self.update_state(pointer, now);
+    }
 
-fn draw_text(frame: &mut [u8], screen_width: u32, font: &Font, text: &str, x: i32, y: i32, w: u32, h: u32, color: [u8; 4]) {
-    let scale = Scale { x: h as f32 * 0.5, y: h as f32 * 0.5 };
-    let v_metrics = font.v_metrics(scale);
-    let glyphs_height = v_metrics.ascent - v_metrics.descent;
-    
-    let glyphs: Vec<_> = font.layout(text, scale, point(0.0, 0.0)).collect();
-    let text_width: f32 = glyphs.iter().map(|g| g.unpositioned().h_metrics().advance_width).sum();
-    
-    let text_x = x as f32 + (w as f32 - text_width) / 2.0;
-    let text_y = y as f32 + (h as f32 - glyphs_height) / 2.0 + v_metrics.ascent;
-    
-    for glyph in font.layout(text, scale, point(text_x, text_y)) {
-        if let Some(bb) = glyph.pixel_bounding_box() {
-            glyph.draw(|gx, gy, v| {
-                if v > 0.1 {
-                    let px = bb.min.x + gx as i32;
-                    let py = bb.min.y + gy as i32;
-                    if px >= 0 && py >= 0 {
-                        let index = ((py as u32 * screen_width) + px as u32) as usize * 4;
-                        if index + 3 < frame.len() {
-                            let text_pixel = Rgba([color[0], color[1], color[2], (color[3] as f32 * v) as u8]);
-                            let old_pixel_slice = &mut frame[index..index + 4];
-                            let mut old_pixel = Rgba([old_pixel_slice[0], old_pixel_slice[1], old_pixel_slice[2], old_pixel_slice[3]]);
-                            old_pixel.blend(&text_pixel);
-                            old_pixel_slice.copy_from_slice(&old_pixel.0);
-                        }
-                    }
-                }
-            });
+    fn settle(&mut self) {
+        if let ButtonState::Animating(transition) = &self.state {
+            self.state = ButtonState::Stable(transition.to);
+        }
+    }
+
+    fn bounds(&self) -> Rect {
+        Rect::new(self.x, self.y, self.width, self.height)
+    }
+
+    fn layout(&mut self, bounds: Rect) {
+        (self.x, self.y, self.width, self.height) = (bounds.x, bounds.y, bounds.width, bounds.height);
+    }
+
+    fn draw(&self, canvas: &mut Canvas, ctx: &DrawContext) {
+        self.draw_button(canvas, ctx.render_cache, ctx.now);
+    }
+
+    fn asset_specs(&self) -> Vec<AssetSpec> {
+        self.button_asset_specs()
+    }
+
+    /// Если пришел текущий или целевой фон, кнопка плавно переходит к нему от запасного,
+    /// даже если в этот момент уже шел переход между состояниями.
+    fn on_asset_arrived(&mut self, key: &str, now: Duration) {
+        let state = match &self.state {
+            ButtonState::Stable(state) => *state,
+            ButtonState::Animating(transition) => transition.to,
+        };
+        if self.asset_key(state) == key {
+            self.state = ButtonState::Animating(Transition { from: None, to: state, start: now });
+        }
+    }
+}
+
+impl Button {
+    fn draw_background(&self, canvas: &mut Canvas, image: &DynamicImage, alpha: f32) {
+        match &self.skin {
+            Some(skin) => draw_nine_slice(canvas, image, skin, self.bounds(), alpha),
+            None => canvas.blit(image, self.x, self.y, alpha),
         }
     }
 }
 
-fn draw_fallback_rect(frame: &mut[u8], screen_width: u32, x: i32, y: i32, w: u32, h: u32, color: [u8; 4]) {
-    for row in 0..h {
-        for col in 0..w {
-            let px = x + col as i32;
-            let py = y + row as i32;
-            if px >= 0 && py >= 0 {
-                let index = ((py as u32 * screen_width) + px as u32) as usize * 4;
-                if index + 3 < frame.len() {
-                    frame[index..index + 4].copy_from_slice(&color);
+// --- Хелперы для отрисовки ---
+
+/// Рисует nine-slice картинку в прямоугольник `rect`: углы без изменений,
+/// края и центр растягиваются или повторяются согласно скину.
+pub(crate) fn draw_nine_slice(canvas: &mut Canvas, image: &DynamicImage, skin: &NineSliceSkin, rect: Rect, alpha_multiplier: f32) {
+    if alpha_multiplier <= 0.01 {
+        return;
+    }
+    let Rect { x, y, width: w, height: h } = rect;
+    let (iw, ih) = image.dimensions();
+    let insets = skin.insets;
+
+    // Края не могут быть шире самой картинки и самого виджета
+    let (src_left, src_right) = shrink_pair(insets.left, insets.right, iw);
+    let (src_top, src_bottom) = shrink_pair(insets.top, insets.bottom, ih);
+    let (dst_left, dst_right) = shrink_pair(src_left, src_right, w);
+    let (dst_top, dst_bottom) = shrink_pair(src_top, src_bottom, h);
+
+    let src_cols = [(0, src_left), (src_left, iw - src_left - src_right), (iw - src_right, src_right)];
+    let src_rows = [(0, src_top), (src_top, ih - src_top - src_bottom), (ih - src_bottom, src_bottom)];
+    let dst_cols = [(0, dst_left), (dst_left, w - dst_left - dst_right), (w - dst_right, dst_right)];
+    let dst_rows = [(0, dst_top), (dst_top, h - dst_top - dst_bottom), (h - dst_bottom, dst_bottom)];
+
+    for row in 0..3 {
+        for col in 0..3 {
+            let mode = match (row, col) {
+                (1, 1) => skin.center,
+                (1, _) | (_, 1) => skin.edges,
+                _ => SliceMode::Stretch, // Углы копируются один в один
+            };
+            let (sx, sw) = src_cols[col];
+            let (sy, sh) = src_rows[row];
+            let (dx, dw) = dst_cols[col];
+            let (dy, dh) = dst_rows[row];
+            if sw == 0 || sh == 0 {
+                continue;
+            }
+            for oy in 0..dh {
+                let src_y = match mode {
+                    SliceMode::Stretch => sy + oy * sh / dh,
+                    SliceMode::Tile => sy + oy % sh,
+                };
+                for ox in 0..dw {
+                    let src_x = match mode {
+                        SliceMode::Stretch => sx + ox * sw / dw,
+                        SliceMode::Tile => sx + ox % sw,
+                    };
+                    let pixel = image.get_pixel(src_x, src_y);
+                    canvas.blend(x + (dx + ox) as i32, y + (dy + oy) as i32, pixel, alpha_multiplier);
                 }
             }
         }
     }
 }
 
+/// Пропорционально ужимает пару краев, если вместе они не помещаются в `total`.
+fn shrink_pair(a: u32, b: u32, total: u32) -> (u32, u32) {
+    if a + b <= total {
+        return (a, b);
+    }
+    let a_scaled = (a as u64 * total as u64 / (a + b) as u64) as u32;
+    (a_scaled, total - a_scaled)
+}
+
+/// Рисует строку по центру прямоугольника; размер шрифта — половина его высоты.
+fn draw_text(canvas: &mut Canvas, font: &Font, text: &str, rect: Rect, color: [u8; 4]) {
+    let scale = Scale { x: rect.height as f32 * 0.5, y: rect.height as f32 * 0.5 };
+    let v_metrics = font.v_metrics(scale);
+    let glyphs_height = v_metrics.ascent - v_metrics.descent;
+
+    let text_width: f32 = font.layout(text, scale, point(0.0, 0.0)).map(|g| g.unpositioned().h_metrics().advance_width).sum();
+
+    let text_x = rect.x as f32 + (rect.width as f32 - text_width) / 2.0;
+    let text_y = rect.y as f32 + (rect.height as f32 - glyphs_height) / 2.0 + v_metrics.ascent;
+    canvas.text(font, text, scale, (text_x, text_y), color);
+}
+
 /// "Текст-Хамелеон": вычисляет, каким должен быть цвет текста, чтобы он был контрастным.
 fn calculate_contrast_color(image: &DynamicImage) -> [u8; 4] {
     let mut total_luminance = 0.0;
@@ -249,33 +386,21 @@ fn calculate_contrast_color(image: &DynamicImage) -> [u8; 4] {
 }
 
 
-// --- TextPanel и TextInput (остаются без изменений, но принимают Arc<Font>) ---
-pub struct TextPanel { pub x: i32, pub y: i32, font: Arc<Font<'static>>, }
+// --- TextPanel (поле ввода живет в text_input.rs) ---
+pub struct TextPanel { pub x: i32, pub y: i32, pub width: u32, pub height: u32, pub text: String, font: Arc<Font<'static>>, binding: Option<String>, }
 impl TextPanel {
-    pub fn new(x: i32, y: i32, font: Arc<Font<'static>>) -> Self { Self { x, y, font } }
-    pub fn draw(&self, text: &str, frame: &mut [u8], screen_width: u32) {
-        draw_text(frame, screen_width, &self.font, text, self.x, self.y, 700, 50, [200, 200, 200, 255]);
-    }
-}
-
-pub struct TextInput {
-    pub x: i32, pub y: i32, pub width: u32, pub height: u32, pub text: String, font: Arc<Font<'static>>,
-    pub is_focused: bool, cursor_timer: Instant, cursor_visible: bool,
+    pub fn new(x: i32, y: i32, font: Arc<Font<'static>>) -> Self { Self { x, y, width: 700, height: 50, text: String::new(), font, binding: None } }
+    pub fn set_text(&mut self, text: String) { self.text = text; }
+    /// Показывать в панели поле состояния приложения `binding`.
+    pub fn with_binding(mut self, binding: &str) -> Self { self.binding = Some(binding.to_string()); self }
 }
-impl TextInput {
-    pub fn new(x: i32, y: i32, width: u32, height: u32, font: Arc<Font<'static>>) -> Self { Self { x, y, width, height, text: String::new(), font, is_focused: false, cursor_timer: Instant::now(), cursor_visible: false } }
-    pub fn is_over(&self, mouse_pos: (i32, i32)) -> bool { mouse_pos.0 >= self.x && mouse_pos.0 <= self.x + self.width as i32 && mouse_pos.1 >= self.y && mouse_pos.1 <= self.y + self.height as i32 }
-    pub fn key_press(&mut self, chars: &str) { if self.is_focused { self.text.push_str(chars); } }
-    pub fn backspace(&mut self) { if self.is_focused { self.text.pop(); } }
-    pub fn draw(&mut self, frame: &mut [u8], screen_width: u32) {
-        let bg_color = if self.is_focused { [50, 50, 60, 255] } else { [30, 30, 40, 255] };
-        draw_fallback_rect(frame, screen_width, self.x, self.y, self.width, self.height, bg_color);
-        
-        let scale = Scale { x: 24.0, y: 24.0 }; let text_color = [220, 220, 220, 255];
-        let v_metrics = self.font.v_metrics(scale); let text_y = self.y + ((self.height as f32 - (v_metrics.ascent - v_metrics.descent)) / 2.0 + v_metrics.ascent) as i32;
-        let glyphs: Vec<_> = self.font.layout(&self.text, scale, point(self.x as f32 + 10.0, text_y as f32)).collect();
-        let cursor_x = glyphs.iter().last().map_or(self.x + 10, |g| (g.position().x + g.unpositioned().h_metrics().advance_width) as i32);
-        for glyph in glyphs { if let Some(bounding_box) = glyph.pixel_bounding_box() { glyph.draw(|gx, gy, v| { if v > 0.1 { let px = bounding_box.min.x + gx as i32; let py = bounding_box.min.y + gy as i32; if px >= 0 && py >= 0 { let index = ((py as u32 * screen_width) + px as u32) as usize * 4; if index + 3 < frame.len() { frame[index..index + 4].copy_from_slice(&text_color); } } } }); } }
-        if self.is_focused { if self.cursor_timer.elapsed().as_millis() > 500 { self.cursor_visible = !self.cursor_visible; self.cursor_timer = Instant::now(); } if self.cursor_visible { let cursor_height = (v_metrics.ascent - v_metrics.descent) as u32; for i in 0..cursor_height { let px = cursor_x; let py = text_y - v_metrics.ascent as i32 + i as i32; if px >= 0 && py >= 0 { let index = ((py as u32 * screen_width) + px as u32) as usize * 4; if index + 3 < frame.len() { frame[index..index + 4].copy_from_slice(&text_color); } } } } }
+impl Widget for TextPanel {
+    fn handle_event(&mut self, _event: &UiEvent, _ctx: &mut EventContext) {}
+    fn bounds(&self) -> Rect { Rect::new(self.x, self.y, self.width, self.height) }
+    fn layout(&mut self, bounds: Rect) { (self.x, self.y, self.width, self.height) = (bounds.x, bounds.y, bounds.width, bounds.height); }
+    fn draw(&self, canvas: &mut Canvas, _ctx: &DrawContext) {
+        draw_text(canvas, &self.font, &self.text, self.bounds(), [200, 200, 200, 255]);
     }
-}
\ No newline at end of file
+    fn binding(&self) -> Option<&str> { self.binding.as_deref() }
+    fn set_value(&mut self, value: &str) { self.set_text(value.to_string()); }
+}
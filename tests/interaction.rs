@@ -0,0 +1,130 @@
+// tests/interaction.rs
+//
+// Сценарии главного экрана через `InputDriver`: мышь, клавиши и текст без окна.
+
+mod support;
+
+use image::Rgba;
+use shadowin::app::{main_screen_actions, ACTION_CLEAR, ACTION_SUBMIT};
+use shadowin::backend::memory::MemoryBackend;
+use shadowin::driver::InputDriver;
+use shadowin::ui::widget::{EditKey, Modifiers};
+use shadowin::ui::widgets::VisualState;
+use shadowin::ui::AppUi;
+use std::sync::Arc;
+use std::time::Duration;
+use support::{assert_snapshot, fixture, font};
+
+fn main_screen() -> InputDriver {
+    let ui = AppUi::new(font(), Arc::new(MemoryBackend::new()));
+    InputDriver::new(ui, main_screen_actions()).with_size(800, 300)
+}
+
+#[test]
+fn submit_shows_typed_text() {
+    let mut driver = main_screen();
+    driver.click_text_input().type_text("hello").click_action(ACTION_SUBMIT);
+
+    assert_eq!(driver.state().message, "Submitted: hello");
+    assert_eq!(driver.state().click_count, 1);
+    assert_eq!(driver.state().text_input_content, "hello");
+}
+
+#[test]
+fn passphrase_welcomes_master() {
+    let mut driver = main_screen();
+    driver.click_text_input().type_text("shadowix").key(EditKey::Backspace).type_text("n");
+    driver.click_action(ACTION_SUBMIT).advance(Duration::from_millis(500));
+
+    assert_eq!(driver.state().message, "Welcome, master.");
+    assert_eq!(driver.state().bg_color, [40, 20, 20, 255]);
+
+    let frame = driver.frame();
+    assert_eq!(*frame.get_pixel(790, 290), Rgba([40, 20, 20, 255]), "background follows the passphrase");
+    assert_snapshot("interaction_welcome_master", &frame);
+}
+
+#[test]
+fn clear_resets_input_and_counter() {
+    let mut driver = main_screen();
+    driver.click_text_input().type_text("first").click_action(ACTION_SUBMIT);
+    driver.click_action(ACTION_SUBMIT);
+    assert_eq!(driver.state().click_count, 2);
+
+    driver.click_action(ACTION_CLEAR);
+
+    let state = driver.state();
+    assert_eq!(state.message, "Cleared.");
+    assert_eq!(state.click_count, 0);
+    assert_eq!(state.text_input_content, "");
+    assert_eq!(state.bg_color, [20, 20, 30, 255]);
+}
+
+#[test]
+fn typing_needs_focus() {
+    let mut driver = main_screen();
+    driver.type_text("ignored");
+    assert_eq!(driver.state().text_input_content, "");
+
+    driver.click_text_input().type_text("kept").click_at(790, 290).type_text(" lost");
+    assert_eq!(driver.state().text_input_content, "kept");
+}
+
+#[test]
+fn word_editing_and_undo() {
+    let mut driver = main_screen();
+    let word = Modifiers { control: true, ..Modifiers::default() };
+    driver.click_text_input().type_text("hello brave world");
+    driver.key_with(EditKey::Left, word).key_with(EditKey::Backspace, word);
+    assert_eq!(driver.state().text_input_content, "hello world");
+
+    driver.key(EditKey::Undo);
+    assert_eq!(driver.state().text_input_content, "hello brave world");
+
+    driver.key(EditKey::SelectAll).type_text("shadowin").click_action(ACTION_SUBMIT);
+    assert_eq!(driver.state().message, "Welcome, master.");
+}
+
+#[test]
+fn caret_blink_follows_virtual_time() {
+    let run = |time: Duration| {
+        let mut driver = main_screen();
+        driver.click_text_input().type_text("blink").advance(time);
+        driver.frame()
+    };
+
+    // Время идет только по часам драйвера, поэтому одинаковые сценарии дают одинаковые кадры
+    assert_eq!(run(Duration::from_millis(600)), run(Duration::from_millis(600)));
+    assert_ne!(run(Duration::ZERO), run(Duration::from_millis(600)), "the caret hides after the blink interval");
+    assert_eq!(run(Duration::ZERO), run(Duration::from_millis(1200)), "and shows up again");
+}
+
+#[test]
+fn button_transition_follows_virtual_time() {
+    let run = |time: Duration| {
+        let mut driver = main_screen();
+        // Переход виден только между разными фонами состояний
+        let cache = driver
+            .ui
+            .asset_specs()
+            .into_iter()
+            .map(|spec| {
+                let shade = match spec.state {
+                    VisualState::Idle => 40,
+                    VisualState::Hovered => 120,
+                    VisualState::Pressed => 200,
+                };
+                (spec.key, fixture(spec.width, spec.height, [shade; 3], [shade; 3], [shade; 3]))
+            })
+            .collect();
+        driver.ui.set_render_cache(cache);
+        driver.click_action(ACTION_CLEAR).advance(time);
+        driver.frame()
+    };
+
+    // Переход кнопки идет по часам драйвера: на полпути кадр один и тот же,
+    // а к концу перехода он перестает меняться
+    assert!(run(Duration::from_millis(100)) == run(Duration::from_millis(100)));
+    assert!(run(Duration::ZERO) != run(Duration::from_millis(100)), "the crossfade advances with virtual time");
+    assert!(run(Duration::from_millis(300)) == run(Duration::from_millis(800)), "the crossfade ends after its duration");
+}
@@ -0,0 +1,133 @@
+// tests/loading.rs
+//
+// Фаза загрузки над `MemoryBackend`: что уходит генератору, в каком порядке
+// и что остается в кэше на диске.
+
+mod support;
+
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use shadowin::backend::memory::MemoryBackend;
+use shadowin::backend::{AssetSpec, GenError, ImageBackend};
+use shadowin::cache::AssetCache;
+use shadowin::loading::{LoadingOptions, LoadingState};
+use shadowin::ui::AppUi;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use support::font;
+
+/// `MemoryBackend`, который объявляет поддержку img2img, чтобы Hovered/Pressed выводились из Idle.
+struct DerivingBackend(MemoryBackend);
+
+impl ImageBackend for DerivingBackend {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn supports_img2img(&self) -> bool {
+        true
+    }
+
+    fn generate_image(&self, spec: &AssetSpec) -> Result<DynamicImage, GenError> {
+        self.0.generate_image(spec)
+    }
+}
+
+/// Папка с кэшем и lock-файлом сидов одного теста; удаляется вместе с ним.
+struct Workspace(PathBuf);
+
+impl Workspace {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("shadowin-loading-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    fn cache_dir(&self) -> PathBuf {
+        self.0.join("cache")
+    }
+
+    fn options(&self) -> LoadingOptions {
+        LoadingOptions {
+            cache_dir: self.cache_dir().to_string_lossy().into_owned(),
+            seed_lock: self.0.join("seeds.lock.json").to_string_lossy().into_owned(),
+            ..LoadingOptions::default()
+        }
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Загружает главный экран через `backend` и возвращает ключи всех его ассетов.
+fn load(workspace: &Workspace, backend: Arc<dyn ImageBackend>) -> Vec<String> {
+    let ui = AppUi::new(font(), Arc::clone(&backend));
+    let keys = ui.asset_specs().into_iter().map(|spec| spec.key).collect();
+    let mut loading = LoadingState::new(font(), backend, ui, workspace.options());
+    assert!(loading.wait().is_some(), "loading finishes with a ready UI");
+    keys
+}
+
+fn solid(width: u32, height: u32, color: [u8; 4]) -> DynamicImage {
+    DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, Rgba(color)))
+}
+
+#[test]
+fn derived_states_are_generated_after_idle() {
+    let workspace = Workspace::new("order");
+    let memory = Arc::new(DerivingBackend(MemoryBackend::new()));
+    let keys = load(&workspace, Arc::clone(&memory) as Arc<dyn ImageBackend>);
+
+    let requests = memory.0.requests();
+    let requested: Vec<&str> = requests.iter().map(|spec| spec.key.as_str()).collect();
+    assert_eq!(requested.len(), keys.len(), "every asset is requested once: {:?}", requested);
+
+    let derived: Vec<&AssetSpec> = requests.iter().filter(|spec| spec.derive_from.is_some()).collect();
+    assert!(!derived.is_empty());
+    for spec in derived {
+        let source = spec.derive_from.as_deref().unwrap();
+        let position = |key: &str| requested.iter().position(|requested| *requested == key).unwrap();
+        assert!(position(source) < position(&spec.key), "{} is requested before {}", source, spec.key);
+        let init = spec.init.as_ref().expect("derived state is drawn from its source");
+        assert!(init.image.is_some());
+    }
+}
+
+#[test]
+fn generated_assets_are_recorded_in_the_cache() {
+    let workspace = Workspace::new("manifest");
+    let probe = AppUi::new(font(), Arc::new(MemoryBackend::new())).asset_specs().remove(0);
+    let image = solid(probe.width, probe.height, [10, 200, 30, 255]);
+    let memory = Arc::new(DerivingBackend(MemoryBackend::new().with_image(&probe.key, image.clone())));
+    let keys = load(&workspace, memory);
+
+    let mut cache = AssetCache::open(workspace.cache_dir()).unwrap();
+    for key in &keys {
+        let metadata = cache.metadata(key).unwrap_or_else(|| panic!("'{}' has metadata in the cache", key));
+        assert_eq!(metadata.backend, "memory");
+        assert!(metadata.requested_seed >= 0, "'{}' gets a seed from the lock file", key);
+    }
+    let stored = cache.metadata(&probe.key).unwrap();
+    let mut spec = probe.clone().with_seed(stored.requested_seed);
+    spec.fit = LoadingOptions::default().fit;
+    spec.filter = LoadingOptions::default().filter;
+    let cached = cache.load(&spec, "memory").expect("the registered image is stored under its spec");
+    assert_eq!(cached.dimensions(), image.dimensions());
+    assert!(cached.to_rgba8() == image.to_rgba8());
+}
+
+#[test]
+fn cached_assets_skip_the_backend() {
+    let workspace = Workspace::new("hit");
+    let first = Arc::new(DerivingBackend(MemoryBackend::new()));
+    load(&workspace, Arc::clone(&first) as Arc<dyn ImageBackend>);
+    assert!(!first.0.requests().is_empty());
+
+    let second = Arc::new(DerivingBackend(MemoryBackend::new()));
+    load(&workspace, Arc::clone(&second) as Arc<dyn ImageBackend>);
+    assert_eq!(second.0.requests(), Vec::new());
+}
@@ -0,0 +1,162 @@
+// tests/snapshots.rs
+//
+// Эталонные снимки виджетов и главного экрана. Новый или измененный эталон:
+// `UPDATE_SNAPSHOTS=1 cargo test --test snapshots`, потом просмотреть PNG в `tests/golden`.
+
+mod support;
+
+use image::DynamicImage;
+use shadowin::backend::memory::MemoryBackend;
+use shadowin::clock::ManualClock;
+use shadowin::headless::{render_screen, ScreenState};
+use shadowin::ui::text_input::TextInput;
+use shadowin::ui::widget::{EventContext, PointerState, Rect, UiEvent, Widget};
+use shadowin::ui::widgets::{Button, TextPanel, VisualState};
+use shadowin::ui::AppUi;
+use shadowin::AppState;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use support::{assert_snapshot, fixture, font, render_widget};
+
+const BUTTON_BOUNDS: Rect = Rect { x: 10, y: 10, width: 200, height: 60 };
+const BUTTON_CANVAS: (u32, u32) = (220, 80);
+
+/// Темные фоны Idle и Hovered дают светлый текст, светлый Pressed — темный.
+fn fixture_background(state: VisualState, width: u32, height: u32) -> Arc<DynamicImage> {
+    match state {
+        VisualState::Idle => fixture(width, height, [30, 40, 70], [10, 15, 30], [60, 90, 160]),
+        VisualState::Hovered => fixture(width, height, [40, 80, 150], [20, 40, 90], [90, 200, 255]),
+        VisualState::Pressed => fixture(width, height, [230, 230, 210], [190, 190, 170], [120, 120, 100]),
+    }
+}
+
+fn button_cache() -> HashMap<String, Arc<DynamicImage>> {
+    [VisualState::Idle, VisualState::Hovered, VisualState::Pressed]
+        .into_iter()
+        .map(|state| (format!("0-{:?}", state), fixture_background(state, BUTTON_BOUNDS.width, BUTTON_BOUNDS.height)))
+        .collect()
+}
+
+fn button() -> Button {
+    Button::new(0, 0, 0, BUTTON_BOUNDS.width, BUTTON_BOUNDS.height, "Submit".to_string(), font())
+}
+
+/// Кнопка в устоявшемся состоянии, куда ее приводит мышь.
+fn settled_button(state: VisualState) -> Button {
+    let mut button = button();
+    button.layout(BUTTON_BOUNDS);
+    let pointer = match state {
+        VisualState::Idle => PointerState { pos: (-1, -1), pressed: false },
+        VisualState::Hovered => PointerState { pos: (100, 40), pressed: false },
+        VisualState::Pressed => PointerState { pos: (100, 40), pressed: true },
+    };
+    button.update(pointer, Duration::ZERO);
+    button.settle();
+    button
+}
+
+#[test]
+fn button_in_each_visual_state() {
+    let cache = button_cache();
+    for (state, name) in [
+        (VisualState::Idle, "button_idle"),
+        (VisualState::Hovered, "button_hovered"),
+        (VisualState::Pressed, "button_pressed"),
+    ] {
+        let image = render_widget(Box::new(settled_button(state)), BUTTON_BOUNDS, &cache, BUTTON_CANVAS, Duration::ZERO);
+        assert_snapshot(name, &image);
+    }
+}
+
+#[test]
+fn button_crossfade_halfway() {
+    let mut button = button();
+    button.start_transition(VisualState::Idle, VisualState::Pressed, Duration::ZERO);
+    // Переход длится 200 мс: кадр на 100 мс — ровно середина
+    let now = Duration::from_millis(100);
+    let image = render_widget(Box::new(button), BUTTON_BOUNDS, &button_cache(), BUTTON_CANVAS, now);
+    assert_snapshot("button_crossfade_half", &image);
+}
+
+#[test]
+fn asset_arriving_mid_transition_fades_in() {
+    let cache = button_cache();
+    let hovering = |now: Duration| {
+        let mut button = button();
+        button.start_transition(VisualState::Idle, VisualState::Hovered, Duration::ZERO);
+        // Фон Hovered пришел под самый конец перехода: он проявляется заново от запасного
+        button.on_asset_arrived("0-Hovered", Duration::from_millis(190));
+        render_widget(Box::new(button), BUTTON_BOUNDS, &cache, BUTTON_CANVAS, now)
+    };
+    let settled = render_widget(Box::new(settled_button(VisualState::Hovered)), BUTTON_BOUNDS, &cache, BUTTON_CANVAS, Duration::ZERO);
+
+    assert!(hovering(Duration::from_millis(200)) != settled, "the arrived background fades in instead of popping");
+    assert!(hovering(Duration::from_millis(390)) == settled, "the fade lasts one transition");
+}
+
+#[test]
+fn button_without_assets_uses_fallback() {
+    let image = render_widget(Box::new(button()), BUTTON_BOUNDS, &HashMap::new(), BUTTON_CANVAS, Duration::ZERO);
+    assert_snapshot("button_fallback", &image);
+}
+
+#[test]
+fn button_past_right_edge_is_clipped() {
+    // Вылезающая за край часть не должна переноситься на следующую строку кадра
+    let bounds = Rect { x: 120, ..BUTTON_BOUNDS };
+    let image = render_widget(Box::new(settled_button(VisualState::Idle)), bounds, &button_cache(), BUTTON_CANVAS, Duration::ZERO);
+    assert_snapshot("button_clipped_right_edge", &image);
+}
+
+#[test]
+fn text_panel_text() {
+    let mut panel = TextPanel::new(0, 0, font());
+    panel.set_text("Submitted: anti-aliased text (Clicks: 3)".to_string());
+    let image = render_widget(Box::new(panel), Rect::new(10, 10, 500, 50), &HashMap::new(), (520, 70), Duration::ZERO);
+    assert_snapshot("text_panel", &image);
+}
+
+#[test]
+fn main_screen_with_focused_input() {
+    let mut ui = AppUi::new(font(), Arc::new(MemoryBackend::new()));
+    let cache = ui
+        .asset_specs()
+        .into_iter()
+        .map(|spec| {
+            let image = fixture_background(spec.state, spec.width, spec.height);
+            (spec.key, image)
+        })
+        .collect();
+    ui.set_render_cache(cache);
+
+    let mut app_state = AppState { message: "AI Renderer is ready.".to_string(), ..AppState::default() };
+    let state = ScreenState {
+        pointer: Some((400, 150)),
+        pressed: true,
+        text: Some("shadowin".to_string()),
+        message: None,
+    };
+    let image = render_screen(&mut ui, &mut app_state, &state, &ManualClock::new(), 800, 300);
+    assert_snapshot("main_screen_focused_input", &image);
+}
+
+/// Поле ввода в фокусе с незавершенным вводом IME `preedit`.
+fn composing_input(password: bool, preedit: &str) -> TextInput {
+    let bounds = Rect::new(10, 10, 300, 40);
+    let mut input = TextInput::new(0, 0, 0, 0, font()).with_password(password);
+    input.layout(bounds);
+    let pointer = PointerState { pos: (100, 30), pressed: true };
+    let mut ctx = EventContext { pointer, now: Duration::ZERO, actions: Vec::new() };
+    input.handle_event(&UiEvent::MouseDown, &mut ctx);
+    input.handle_event(&UiEvent::Preedit { text: preedit.to_string(), cursor: None }, &mut ctx);
+    input
+}
+
+#[test]
+fn password_masks_ime_composition() {
+    let render = |input: TextInput| render_widget(Box::new(input), Rect::new(10, 10, 300, 40), &HashMap::new(), (320, 60), Duration::ZERO);
+    let composing = render(composing_input(true, "пароль"));
+    assert_eq!(composing, render(composing_input(false, "******")), "the composition is masked like typed text");
+    assert_ne!(composing, render(composing_input(false, "пароль")));
+}
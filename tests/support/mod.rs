@@ -0,0 +1,191 @@
+// tests/support/mod.rs
+//
+// Общая часть snapshot-тестов: отрисовка без окна, картинки-фикстуры и сравнение
+// с эталонными PNG в `tests/golden`.
+
+#![allow(dead_code)]
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use rusttype::Font;
+use shadowin::ui::canvas::Canvas;
+use shadowin::ui::widget::{draw_tree, DrawContext, Rect, Widget};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Фон, на котором рисуются отдельные виджеты.
+pub const BACKGROUND: [u8; 4] = [20, 20, 30, 255];
+
+/// Если задана эта переменная окружения, эталоны перезаписываются текущим результатом.
+const UPDATE_ENV: &str = "UPDATE_SNAPSHOTS";
+
+pub fn font() -> Arc<Font<'static>> {
+    let font_data = include_bytes!("../../assets/font.ttf");
+    Arc::new(Font::try_from_bytes(font_data).expect("assets/font.ttf is a valid font"))
+}
+
+/// Вертикальный градиент от `top` к `bottom` с рамкой цвета `border` толщиной 2 px:
+/// по рамке видно, что картинка легла целиком и не съехала.
+pub fn fixture(width: u32, height: u32, top: [u8; 3], bottom: [u8; 3], border: [u8; 3]) -> Arc<DynamicImage> {
+    let image = RgbaImage::from_fn(width, height, |x, y| {
+        if x < 2 || y < 2 || x + 2 >= width || y + 2 >= height {
+            return Rgba([border[0], border[1], border[2], 255]);
+        }
+        let t = y as f32 / (height.max(2) - 1) as f32;
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Rgba([mix(top[0], bottom[0]), mix(top[1], bottom[1]), mix(top[2], bottom[2]), 255])
+    });
+    Arc::new(DynamicImage::ImageRgba8(image))
+}
+
+/// Рисует один виджет (с детьми) в буфер `width` x `height`, положив его в `bounds`;
+/// `now` — время кадра для анимаций.
+pub fn render_widget(
+    widget: Box<dyn Widget>,
+    bounds: Rect,
+    cache: &HashMap<String, Arc<DynamicImage>>,
+    (width, height): (u32, u32),
+    now: Duration,
+) -> RgbaImage {
+    let mut widgets = [widget];
+    widgets[0].layout(bounds);
+    let mut image = RgbaImage::new(width, height);
+    let mut canvas = Canvas::new(&mut image, width, height);
+    canvas.clear(BACKGROUND);
+    draw_tree(&widgets, &mut canvas, &DrawContext { now, render_cache: cache });
+    image
+}
+
+/// Насколько снимок может отличаться от эталона.
+#[derive(Clone, Copy, Debug)]
+pub struct Tolerance {
+    /// Порог различия одного пикселя в YIQ, от 0 до 1, как в pixelmatch:
+    /// 0.1 пропускает разницу сглаживания, но не смену цвета.
+    pub threshold: f32,
+    /// Доля пикселей, которым разрешено отличаться сильнее порога.
+    pub max_diff_ratio: f32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self { threshold: 0.1, max_diff_ratio: 0.001 }
+    }
+}
+
+/// Сравнивает снимок с `tests/golden/{name}.png`. С `UPDATE_SNAPSHOTS` записывает снимок
+/// как новый эталон. Без эталона или при расхождении кладет снимок (и карту различий)
+/// в папку временных файлов тестов и падает.
+pub fn assert_snapshot(name: &str, actual: &RgbaImage) {
+    assert_snapshot_with(name, actual, Tolerance::default());
+}
+
+pub fn assert_snapshot_with(name: &str, actual: &RgbaImage, tolerance: Tolerance) {
+    let golden_path = golden_dir().join(format!("{}.png", name));
+    if std::env::var_os(UPDATE_ENV).is_some() {
+        std::fs::create_dir_all(golden_dir()).unwrap();
+        actual.save(&golden_path).unwrap();
+        println!("Snapshot '{}' written to '{}'.", name, golden_path.display());
+        return;
+    }
+    // Пропавший эталон — ошибка, а не повод записать новый: иначе опечатка в имени проходит молча
+    if !golden_path.exists() {
+        let actual_path = save_artifact(name, "actual", actual);
+        panic!(
+            "snapshot '{}' has no golden image; actual image saved to '{}'. Run with {}=1 to record it.",
+            name,
+            actual_path.display(),
+            UPDATE_ENV
+        );
+    }
+
+    let expected = image::open(&golden_path).unwrap_or_else(|e| panic!("failed to read '{}': {}", golden_path.display(), e)).to_rgba8();
+    if expected.dimensions() != actual.dimensions() {
+        let actual_path = save_artifact(name, "actual", actual);
+        panic!(
+            "snapshot '{}': size {:?} differs from golden {:?}; actual image saved to '{}'",
+            name,
+            actual.dimensions(),
+            expected.dimensions(),
+            actual_path.display()
+        );
+    }
+
+    let (diff, different) = compare(&expected, actual, tolerance.threshold);
+    let allowed = (tolerance.max_diff_ratio * (actual.width() * actual.height()) as f32) as usize;
+    if different > allowed {
+        let actual_path = save_artifact(name, "actual", actual);
+        let diff_path = save_artifact(name, "diff", &diff);
+        panic!(
+            "snapshot '{}': {} pixels differ from golden (allowed {}); actual: '{}', diff: '{}'. \
+             Run with {}=1 to accept the new image.",
+            name,
+            different,
+            allowed,
+            actual_path.display(),
+            diff_path.display(),
+            UPDATE_ENV
+        );
+    }
+}
+
+/// Карта различий: эталон бледно-серым, отличающиеся пиксели красным.
+/// Возвращает ее и число отличающихся пикселей.
+fn compare(expected: &RgbaImage, actual: &RgbaImage, threshold: f32) -> (RgbaImage, usize) {
+    // Наибольшее возможное значение `color_delta`
+    const MAX_DELTA: f32 = 35215.0;
+    let max_delta = MAX_DELTA * threshold * threshold;
+
+    let mut different = 0;
+    let diff = RgbaImage::from_fn(actual.width(), actual.height(), |x, y| {
+        let (a, b) = (expected.get_pixel(x, y), actual.get_pixel(x, y));
+        if color_delta(a, b) > max_delta {
+            different += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            let gray = (255.0 - (255.0 - luma(a)) * 0.1) as u8;
+            Rgba([gray, gray, gray, 255])
+        }
+    });
+    (diff, different)
+}
+
+/// Воспринимаемая разница цветов в пространстве YIQ (Kotsarenko, Ramos), как в pixelmatch.
+fn color_delta(a: &Rgba<u8>, b: &Rgba<u8>) -> f32 {
+    let (ya, ia, qa) = yiq(a);
+    let (yb, ib, qb) = yiq(b);
+    let (dy, di, dq) = (ya - yb, ia - ib, qa - qb);
+    0.5053 * dy * dy + 0.299 * di * di + 0.1957 * dq * dq
+}
+
+fn yiq(pixel: &Rgba<u8>) -> (f32, f32, f32) {
+    let [r, g, b] = premultiplied(pixel);
+    (
+        r * 0.298_895_3 + g * 0.586_622_5 + b * 0.114_482_2,
+        r * 0.595_977_99 - g * 0.274_176_1 - b * 0.321_801_9,
+        r * 0.211_470_17 - g * 0.522_617_1 + b * 0.311_146_94,
+    )
+}
+
+fn luma(pixel: &Rgba<u8>) -> f32 {
+    yiq(pixel).0
+}
+
+/// Цвет, смешанный с белым по альфе: прозрачные пиксели сравниваются как фон.
+fn premultiplied(pixel: &Rgba<u8>) -> [f32; 3] {
+    let alpha = pixel[3] as f32 / 255.0;
+    let blend = |c: u8| 255.0 + (c as f32 - 255.0) * alpha;
+    [blend(pixel[0]), blend(pixel[1]), blend(pixel[2])]
+}
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+fn save_artifact(name: &str, kind: &str, image: &RgbaImage) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("snapshots");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{}.{}.png", name, kind));
+    image.save(&path).unwrap();
+    path
+}